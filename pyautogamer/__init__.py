@@ -3,6 +3,7 @@ from .autogamer_bindings import *
 __all__ = [
     "Game",
     "Level",
+    "LoadWarning",
     "PhysicsEngine",
     "Entity",
     "TileMap",
@@ -27,4 +28,5 @@ __all__ = [
     "Health",
     "ViewportTarget",
     "Wallet",
+    "Inventory",
 ]
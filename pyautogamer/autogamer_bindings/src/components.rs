@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::collections::HashMap;
 
 use autogamer as ag;
 use pyo3::prelude::*;
@@ -218,6 +219,7 @@ components! {
     Health,
     ViewportTarget,
     Wallet,
+    Inventory,
 }
 
 fn update_component<C: PyWriteComponent>(
@@ -429,8 +431,8 @@ impl From<(Arc<Mutex<ag::Level>>, specs::Entity, ag::PhysicsCollider)> for Physi
 #[pymethods]
 impl PhysicsCollider {
     #[new]
-    #[args("*", shape, offset="None", density="0.0", collision_groups="None")]
-    pub fn new(shape: &PyAny, offset: Option<(f64, f64)>, density: f64, collision_groups: Option<CollisionGroups>) -> PyResult<Self> {
+    #[args("*", shape, offset="None", density="0.0", collision_groups="None", one_way="false")]
+    pub fn new(shape: &PyAny, offset: Option<(f64, f64)>, density: f64, collision_groups: Option<CollisionGroups>, one_way: bool) -> PyResult<Self> {
         let shape = Shape::to_shape(shape)
             .ok_or_else(|| PyValueError::new_err("Unknown shape"))?;
         let (offset_x, offset_y) = offset.unwrap_or_default();
@@ -445,6 +447,7 @@ impl PhysicsCollider {
                 offset,
                 density,
                 collision_groups,
+                one_way,
                 ..ag::PhysicsCollider::default()
             },
         })
@@ -543,7 +546,8 @@ impl CharacterSprites {
 /// An entity with this component will respond to arrow key presses by setting
 /// its velocity to the configured values. `left_velocity` and `right_velocity`
 /// will be applied to the x-axis velocity. `jump_velocity` will be applied to
-/// the y-axis velocity.
+/// the y-axis velocity. `climb_velocity` will be applied to the y-axis velocity
+/// while climbing a ladder with the up and down arrow keys.
 #[pyclass]
 #[derive(Debug, Clone)]
 pub struct PlatformerControls {
@@ -575,11 +579,13 @@ impl PlatformerControls {
         horizontal_velocity,
         jump_velocity,
         midair_horizontal_multiplier = "1.0",
+        climb_velocity = "0.0",
     )]
     pub fn new(
         horizontal_velocity: f64,
         jump_velocity: f64,
         midair_horizontal_multiplier: f64,
+        climb_velocity: f64,
     ) -> Self {
         Self {
             entity: None,
@@ -587,6 +593,7 @@ impl PlatformerControls {
                 horizontal_velocity,
                 jump_velocity,
                 midair_horizontal_multiplier,
+                climb_velocity,
                 ..ag::PlatformerControls::default()
             },
         }
@@ -635,6 +642,17 @@ impl PlatformerControls {
         self.component.midair_horizontal_multiplier = midair_horizontal_multiplier;
         update_component(&self.entity, self);
     }
+
+    #[getter]
+    pub fn climb_velocity(&self) -> f64 {
+        self.component.climb_velocity
+    }
+
+    #[setter]
+    pub fn set_climb_velocity(&mut self, climb_velocity: f64) {
+        self.component.climb_velocity = climb_velocity;
+        update_component(&self.entity, self);
+    }
 }
 
 /// The health of an entity
@@ -799,3 +817,84 @@ impl Wallet {
         update_component(&self.entity, self);
     }
 }
+
+/// The number of each kind of item collected by this entity so far, indexed by
+/// the name of the item
+///
+/// This component must be present for an entity to be able to pick up items or
+/// open doors.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct Inventory {
+    entity: Option<(Arc<Mutex<ag::Level>>, specs::Entity)>,
+    component: ag::Inventory,
+}
+
+impl From<ag::Inventory> for Inventory {
+    fn from(component: ag::Inventory) -> Self {
+        Self {
+            entity: None,
+            component,
+        }
+    }
+}
+
+impl From<(Arc<Mutex<ag::Level>>, specs::Entity, ag::Inventory)> for Inventory {
+    fn from((level, entity, component): (Arc<Mutex<ag::Level>>, specs::Entity, ag::Inventory)) -> Self {
+        let entity = Some((level, entity));
+        Self {entity, component}
+    }
+}
+
+#[pymethods]
+impl Inventory {
+    #[new]
+    pub fn new() -> Self {
+        Self {
+            entity: None,
+            component: ag::Inventory::default(),
+        }
+    }
+
+    /// Returns a copy of this component
+    ///
+    /// Modifying a copy of a component does not modify the original component
+    /// it was copied from
+    pub fn copy(&self) -> Self {
+        Self {
+            entity: None,
+            component: self.component.clone(),
+        }
+    }
+
+    /// Returns the number of items with the given name in this inventory
+    pub fn count(&self, name: &str) -> u32 {
+        self.component.count(name)
+    }
+
+    /// Adds the given number of items with the given name to this inventory
+    #[args(count = 1)]
+    pub fn add(&mut self, name: &str, count: u32) {
+        self.component.add(name, count);
+        update_component(&self.entity, self);
+    }
+
+    /// Removes the given number of items with the given name from this
+    /// inventory
+    ///
+    /// Returns False and leaves the inventory unchanged if there are not enough
+    /// items to remove.
+    #[args(count = 1)]
+    pub fn remove(&mut self, name: &str, count: u32) -> bool {
+        let removed = self.component.remove(name, count);
+        update_component(&self.entity, self);
+        removed
+    }
+
+    /// Returns a dictionary of the number of each kind of item in this
+    /// inventory
+    #[getter]
+    pub fn items(&self) -> HashMap<String, u32> {
+        self.component.0.clone()
+    }
+}
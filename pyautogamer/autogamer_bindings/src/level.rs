@@ -3,7 +3,7 @@ use std::sync::Arc;
 use autogamer as ag;
 use pyo3::prelude::*;
 use pyo3::PyTraverseError;
-use pyo3::types::PyTuple;
+use pyo3::types::{PyTuple, PyDict};
 use pyo3::gc::{PyGCProtocol, PyVisit};
 use pyo3::exceptions::PyValueError;
 use parking_lot::Mutex;
@@ -11,6 +11,51 @@ use parking_lot::Mutex;
 use crate::*;
 use crate::ui::*;
 
+/// A non-fatal problem found while loading a map
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct LoadWarning {
+    /// A description of the problem
+    #[pyo3(get)]
+    message: String,
+    /// The name of the layer that caused the problem (if any)
+    #[pyo3(get)]
+    layer: Option<String>,
+    /// The name of the tileset that caused the problem (if any)
+    #[pyo3(get)]
+    tileset: Option<String>,
+    /// The ID of the tile that caused the problem (if any)
+    #[pyo3(get)]
+    tile_id: Option<u32>,
+    /// The ID of the object that caused the problem (if any)
+    #[pyo3(get)]
+    object_id: Option<u32>,
+}
+
+impl From<ag::LoadWarning> for LoadWarning {
+    fn from(warning: ag::LoadWarning) -> Self {
+        Self {
+            message: warning.to_string(),
+            layer: warning.layer().map(String::from),
+            tileset: warning.tileset().map(String::from),
+            tile_id: warning.tile().map(|tile| tile.gid()),
+            object_id: warning.object(),
+        }
+    }
+}
+
+/// An entity that matched a template registered from Python, recorded so the
+/// template can be called once the level is no longer locked
+#[derive(Debug)]
+struct PendingTemplate {
+    /// The index of the template in `Level::templates`
+    index: usize,
+    entity: specs::Entity,
+    origin: ag::TemplateOrigin,
+    tile_type: String,
+    props: Vec<(String, ag::PropertyValue)>,
+}
+
 #[pyclass(subclass, gc, extends=Screen)]
 #[derive(Debug)]
 pub struct Level {
@@ -18,6 +63,10 @@ pub struct Level {
     game: Py<Game>,
     #[pyo3(get)]
     physics: Py<PhysicsEngine>,
+    /// The templates registered from Python and what triggers them, in the
+    /// order they were registered
+    templates: Vec<(ag::TemplateTrigger, PyObject)>,
+    pending_templates: Arc<Mutex<Vec<PendingTemplate>>>,
 }
 
 #[pyproto]
@@ -27,10 +76,15 @@ impl PyGCProtocol for Level {
             level: _,
             game,
             physics,
+            templates,
+            pending_templates: _,
         } = self;
 
         visit.call(game)?;
         visit.call(physics)?;
+        for (_, template) in templates {
+            visit.call(template)?;
+        }
 
         Ok(())
     }
@@ -50,7 +104,13 @@ impl Level {
 
         let base = Screen::new(game.clone());
 
-        let level = Self {level, game, physics};
+        let level = Self {
+            level,
+            game,
+            physics,
+            templates: Vec::new(),
+            pending_templates: Arc::default(),
+        };
         Ok((level, base))
     }
 
@@ -73,17 +133,96 @@ impl Level {
         Entity::new(self.level.clone(), entity)
     }
 
+    /// Registers a function that will be called for every entity created from
+    /// the map that has the given tile type or custom property
+    ///
+    /// If neither `tile_type` nor `prop` is provided, the function is called
+    /// for every entity. The function is called with the `Entity`, its tile
+    /// type and a dict of its custom properties.
+    ///
+    /// Templates registered from Python are only called once every entity in
+    /// the map has been created and had the built-in templates applied. They
+    /// are then called for each entity in the order the entities were created,
+    /// and for each entity in the order the templates were registered.
+    #[args(template, "*", tile_type = "None", prop = "None")]
+    pub fn register_template(
+        &mut self,
+        template: PyObject,
+        tile_type: Option<String>,
+        prop: Option<String>,
+    ) -> PyResult<()> {
+        let trigger = match (tile_type, prop) {
+            (None, None) => ag::TemplateTrigger::Always,
+            (Some(tile_type), None) => ag::TemplateTrigger::TileType(tile_type),
+            (None, Some(prop)) => ag::TemplateTrigger::Property(prop),
+            (Some(_), Some(_)) => return Err(PyValueError::new_err(
+                "only one of `tile_type` or `prop` may be provided",
+            )),
+        };
+
+        let index = self.templates.len();
+        self.templates.push((trigger.clone(), template));
+
+        register_pending_template(&mut self.level.lock(), trigger, index, &self.pending_templates);
+
+        Ok(())
+    }
+
     /// Loads a map into this level, automatically discovering entities and
     /// components based on the contents of the map.
-    pub fn load(&mut self, py: Python, map: &TileMap) -> PyResult<()> {
-        let mut game = self.game.borrow_mut(py);
-        let mut image_cache = game.inner_mut().image_cache_mut();
+    ///
+    /// Returns a list of any non-fatal problems found while loading the map.
+    /// If `strict` is True, those problems are raised as errors instead.
+    ///
+    /// If the map sets the `gravity_x` or `gravity_y` properties, the gravity
+    /// of the physics engine is updated to match.
+    ///
+    /// A level may only be loaded once. If loading fails, whether because of
+    /// a problem with the map or because a template registered from Python
+    /// raised an exception, part of the map may already have been added to
+    /// the level, so any later call to `load` raises an error. Create a new
+    /// `Level` to try again.
+    #[args(strict = "false")]
+    pub fn load(&mut self, py: Python, map: &TileMap, strict: bool) -> PyResult<Vec<LoadWarning>> {
+        let warnings = {
+            let mut game = self.game.borrow_mut(py);
+            let mut image_cache = game.inner_mut().image_cache_mut();
+
+            let mut level = self.level.lock();
+            level.set_strict(strict);
+            level.load(
+                map.inner(),
+                &mut image_cache,
+            ).map_err(|err| PyValueError::new_err(err.to_string()))?
+        };
+
+        self.apply_pending_templates(py)?;
+
+        if let Some(gravity) = self.level.lock().config().gravity {
+            self.physics.borrow_mut(py).inner_mut().set_gravity(gravity);
+        }
 
-        self.level.lock().load(
-            map.base_dir(),
-            map.inner(),
-            &mut image_cache,
-        ).map_err(|err| PyValueError::new_err(err.to_string()))
+        Ok(warnings.into_iter().map(LoadWarning::from).collect())
+    }
+
+    /// The path of the music that should play in the background of this
+    /// level, as set by the `background_music` property of the map
+    #[getter]
+    pub fn background_music(&self) -> Option<String> {
+        let level = self.level.lock();
+        level.config().background_music.as_ref()
+            .map(|path| path.to_string_lossy().into_owned())
+    }
+
+    /// The number of seconds left before the time limit of the level runs out,
+    /// or None if the map does not set the `time_limit` property
+    ///
+    /// Counts the real time since the map was loaded, as of the last call to
+    /// `update`. Time keeps counting even if `update` is not called, e.g.
+    /// while the game is paused.
+    #[getter]
+    pub fn time_remaining(&self) -> Option<f64> {
+        self.level.lock().time_remaining().map(|time| time.as_secs_f64())
     }
 
     pub fn load_sprites(&mut self, py: Python, sheet: &CharacterSpritesheet) -> PyResult<CharacterSprites> {
@@ -114,3 +253,86 @@ impl Level {
             .map_err(|err| PyValueError::new_err(err.to_string()))
     }
 }
+
+impl Level {
+    /// Calls the templates registered from Python for every entity recorded
+    /// while the map was loaded
+    fn apply_pending_templates(&self, py: Python) -> PyResult<()> {
+        // The level must be unlocked before calling into Python since the
+        // templates may access it through the entity they are given
+        let pending_templates = std::mem::take(&mut *self.pending_templates.lock());
+        for pending in pending_templates {
+            let PendingTemplate {index, entity, origin, tile_type, props} = pending;
+
+            let entity = Entity::new(self.level.clone(), entity);
+            let py_props = PyDict::new(py);
+            for (prop, value) in props {
+                py_props.set_item(prop, property_value_to_py(py, value))?;
+            }
+
+            let (_, template) = &self.templates[index];
+            template.call1(py, (entity, tile_type, py_props))
+                .map_err(|err| PyValueError::new_err(format!("{} ({})", err, origin)))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Registers a template that records each entity it is applied to so that the
+/// template registered from Python at the given index can be called once the
+/// level has been loaded
+fn register_pending_template(
+    level: &mut ag::Level,
+    trigger: ag::TemplateTrigger,
+    index: usize,
+    pending_templates: &Arc<Mutex<Vec<PendingTemplate>>>,
+) {
+    // Python code can't safely access the level while it is being loaded,
+    // so the template is only recorded here and called later
+    let pending_templates = pending_templates.clone();
+    level.register_template(trigger, move |
+        entity: &ag::EntityEditor,
+        origin: ag::TemplateOrigin,
+        tile_type: &str,
+        props: &dyn ag::CustomProps,
+    | {
+        pending_templates.lock().push(PendingTemplate {
+            index,
+            entity: entity.entity(),
+            origin,
+            tile_type: tile_type.to_string(),
+            props: props.iter_props()
+                .map(|(prop, value)| {
+                    let value = match value {
+                        // Resolve file paths relative to the map
+                        ag::PropertyValue::FileValue(path) => ag::PropertyValue::FileValue(
+                            props.base_dir().join(path).to_string_lossy().into_owned(),
+                        ),
+                        value => value.clone(),
+                    };
+
+                    (prop.to_string(), value)
+                })
+                .collect(),
+        });
+
+        Ok(())
+    });
+}
+
+fn property_value_to_py(py: Python, value: ag::PropertyValue) -> PyObject {
+    use ag::PropertyValue::*;
+    match value {
+        BoolValue(value) => value.to_object(py),
+        FloatValue(value) => value.to_object(py),
+        IntValue(value) => value.to_object(py),
+        // Colors are stored by Tiled as 0xAARRGGBB
+        ColorValue(argb) => {
+            let [a, r, g, b] = argb.to_be_bytes();
+            (r, g, b, a).to_object(py)
+        },
+        StringValue(value) |
+        FileValue(value) => value.to_object(py),
+    }
+}
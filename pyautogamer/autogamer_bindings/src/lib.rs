@@ -37,6 +37,7 @@ pub fn autogamer_bindings(py: Python, pymod: &PyModule) -> PyResult<()> {
 
     pymod.add_class::<Game>()?;
     pymod.add_class::<Level>()?;
+    pymod.add_class::<LoadWarning>()?;
     pymod.add_class::<Entity>()?;
     pymod.add_class::<TileMap>()?;
     pymod.add_class::<CharacterSpritesheet>()?;
@@ -1,4 +1,4 @@
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
 use autogamer as ag;
 use pyo3::prelude::*;
@@ -12,15 +12,10 @@ use pyo3::exceptions::PyValueError;
 #[pyclass]
 #[derive(Debug)]
 pub struct TileMap {
-    base_dir: PathBuf,
     map: ag::TileMap,
 }
 
 impl TileMap {
-    pub fn base_dir(&self) -> &Path {
-        &self.base_dir
-    }
-
     pub fn inner(&self) -> &ag::TileMap {
         &self.map
     }
@@ -31,14 +26,10 @@ impl TileMap {
     #[new]
     pub fn new(path: &str) -> PyResult<Self> {
         let path: &Path = path.as_ref();
-        let base_dir = path.parent()
-            .ok_or_else(|| PyValueError::new_err("Path to tiled map file did not have a valid parent directory"))?
-            .to_path_buf();
-
         let map = ag::TileMap::open(path)
             .map_err(|err| PyValueError::new_err(err.to_string()))?;
 
-        Ok(Self {base_dir, map})
+        Ok(Self {map})
     }
 
     #[getter]
@@ -26,6 +26,7 @@ class PlayScreen(LevelScreen):
             horizontal_velocity=250.0,
             jump_velocity=-450.0,
             midair_horizontal_multiplier=0.7,
+            climb_velocity=200.0,
         ))
         player.add(PhysicsBody(mass=40.0))
         player.add(PhysicsCollider(
@@ -35,6 +36,7 @@ class PlayScreen(LevelScreen):
         ))
         player.add(Health(6))
         player.add(Wallet())
+        player.add(Inventory())
         player.add(ViewportTarget())
         player.add(player_sprites.default_sprite())
         player.add(player_sprites)
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use crate::{TileImage, Image};
+
+/// A single frame of an animation
+#[derive(Debug, Clone)]
+pub struct AnimationFrame {
+    /// The image displayed during this frame
+    pub image: TileImage,
+    /// The duration of the frame in ms
+    pub duration: u32,
+}
+
+impl AnimationFrame {
+    /// Updates the given image so that it displays this frame
+    ///
+    /// Only the image data is replaced. The size, alignment, and other
+    /// parameters of the image (flips, rotation, alpha, etc.) are left as is.
+    /// Animation frames always come from the same tileset, so those parameters
+    /// should be the same for every frame.
+    pub fn update_image(&self, image: &mut Image) {
+        let TileImage {id, src, size: _, align: _, offset: _} = self.image;
+
+        image.id = id;
+        image.src = src;
+    }
+}
+
+/// An animation that loops through a sequence of frames
+///
+/// Animations have no state of their own. The current frame is always computed
+/// from the time elapsed since the level started. That means that every
+/// instance of the same animation will always display the same frame.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    frames: Vec<AnimationFrame>,
+    /// The sum of the durations of all the frames in ms
+    total_duration: u64,
+}
+
+impl Animation {
+    /// Creates a new animation from the given frames
+    ///
+    /// # Panics
+    ///
+    /// Panics if no frames are provided
+    pub fn new(frames: Vec<AnimationFrame>) -> Self {
+        assert!(!frames.is_empty(), "bug: an animation must have at least one frame");
+
+        let total_duration = frames.iter()
+            .map(|frame| frame.duration as u64)
+            .sum();
+
+        Self {frames, total_duration}
+    }
+
+    pub fn frames(&self) -> &[AnimationFrame] {
+        &self.frames
+    }
+
+    /// Returns the frame that should be displayed at the given time
+    pub fn frame_at(&self, elapsed: Duration) -> &AnimationFrame {
+        // Avoid dividing by zero if all frames have a duration of zero
+        if self.total_duration == 0 {
+            return &self.frames[0];
+        }
+
+        let mut time = (elapsed.as_millis() % self.total_duration as u128) as u64;
+        for frame in &self.frames {
+            let duration = frame.duration as u64;
+            if time < duration {
+                return frame;
+            }
+            time -= duration;
+        }
+
+        unreachable!("bug: time should always be less than the total duration of the animation")
+    }
+}
@@ -0,0 +1,210 @@
+use std::fs;
+use std::io;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf, Component};
+
+/// A source of asset files (maps, tilesets, images, etc.)
+///
+/// Paths passed to an asset source are always relative to the root of that
+/// source. Sources can be backed by anything that can provide the bytes of a
+/// file given its path (a directory, files embedded in the executable, an
+/// archive, etc.)
+pub trait AssetSource: fmt::Debug + Send + Sync {
+    /// Returns a path that uniquely identifies the asset at the given path
+    ///
+    /// Two paths that refer to the same asset must produce the same canonical
+    /// path. Returns an error if the asset does not exist.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Reads the entire contents of the asset at the given path
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+}
+
+/// A shared reference to an asset source
+pub type SharedAssetSource = Arc<dyn AssetSource>;
+
+/// Returns the asset source used to read files relative to the current
+/// directory when no other source is provided
+///
+/// The same source is always returned since the image cache identifies each
+/// image by its source. Sharing the source allows an image used by several
+/// maps and spritesheets to be loaded only once.
+pub fn filesystem_source() -> SharedAssetSource {
+    static SOURCE: OnceLock<SharedAssetSource> = OnceLock::new();
+    SOURCE.get_or_init(|| Arc::new(DirSource::new(""))).clone()
+}
+
+/// An asset source that reads files from a directory on the filesystem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirSource {
+    root: PathBuf,
+}
+
+impl DirSource {
+    /// Creates a source that reads files relative to the given directory
+    ///
+    /// Use an empty path to read files relative to the current directory.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {root: root.into()}
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl AssetSource for DirSource {
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::canonicalize(self.root.join(path))
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(self.root.join(path))
+    }
+}
+
+/// An asset source that stores all of its files in memory
+///
+/// Useful for assets that are embedded in the executable, unpacked from an
+/// archive, or generated by code.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemorySource {
+    /// Map from the normalized path of each file to its contents
+    files: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file to the source, replacing any file already at that path
+    pub fn insert<P: AsRef<Path>, D: Into<Vec<u8>>>(&mut self, path: P, data: D) {
+        self.files.insert(normalize(path.as_ref()), data.into());
+    }
+}
+
+impl AssetSource for MemorySource {
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        let path = normalize(path);
+        if self.files.contains_key(&path) {
+            Ok(path)
+        } else {
+            Err(not_found(&path))
+        }
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let path = normalize(path);
+        self.files.get(&path).cloned().ok_or_else(|| not_found(&path))
+    }
+}
+
+/// An asset source that reads files from a zip archive
+///
+/// Files are decompressed each time they are read. Only available with the
+/// `zip` feature.
+#[cfg(feature = "zip")]
+pub struct ZipSource<R> {
+    archive: parking_lot::Mutex<zip::ZipArchive<R>>,
+}
+
+#[cfg(feature = "zip")]
+impl<R> fmt::Debug for ZipSource<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ZipSource")
+            .field("archive", &"ZipArchive { .. }")
+            .finish()
+    }
+}
+
+#[cfg(feature = "zip")]
+impl ZipSource<fs::File> {
+    /// Opens the zip archive at the given path on the filesystem
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::new(fs::File::open(path)?)
+    }
+}
+
+#[cfg(feature = "zip")]
+impl<R: io::Read + io::Seek> ZipSource<R> {
+    /// Creates a source that reads files from the zip archive in the given
+    /// reader
+    pub fn new(reader: R) -> io::Result<Self> {
+        let archive = zip::ZipArchive::new(reader).map_err(zip_error)?;
+        Ok(Self {archive: parking_lot::Mutex::new(archive)})
+    }
+}
+
+#[cfg(feature = "zip")]
+impl<R: io::Read + io::Seek + fmt::Debug + Send> AssetSource for ZipSource<R> {
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        let path = normalize(path);
+        let found = self.archive.lock().by_name(&zip_name(&path)).map(|_| ());
+        match found {
+            Ok(()) => Ok(path),
+            Err(zip::result::ZipError::FileNotFound) => Err(not_found(&path)),
+            Err(err) => Err(zip_error(err)),
+        }
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let path = normalize(path);
+        let mut archive = self.archive.lock();
+        let mut file = match archive.by_name(&zip_name(&path)) {
+            Ok(file) => file,
+            Err(zip::result::ZipError::FileNotFound) => return Err(not_found(&path)),
+            Err(err) => return Err(zip_error(err)),
+        };
+
+        let mut data = Vec::new();
+        io::Read::read_to_end(&mut file, &mut data)?;
+        Ok(data)
+    }
+}
+
+/// Returns the name of the file at the given normalized path within a zip
+/// archive
+///
+/// Zip archives always use `/` as the path separator, regardless of platform.
+#[cfg(feature = "zip")]
+fn zip_name(path: &Path) -> String {
+    path.iter()
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(feature = "zip")]
+fn zip_error(err: zip::result::ZipError) -> io::Error {
+    match err {
+        zip::result::ZipError::Io(err) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, err),
+    }
+}
+
+/// Removes any `.` and `..` components from the given path without accessing
+/// the filesystem
+///
+/// The root of the path is discarded since every path is relative to the root
+/// of the asset source.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::ParentDir => {
+                normalized.pop();
+            },
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {},
+        }
+    }
+
+    normalized
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("no asset found at `{}`", path.display()))
+}
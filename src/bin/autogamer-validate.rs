@@ -0,0 +1,57 @@
+//! Checks Tiled maps for problems without opening a window
+//!
+//! Usage: autogamer-validate [--deny-warnings] <map.tmx>...
+//!
+//! Exits with a non-zero status if any map could not be opened or has errors.
+//! With `--deny-warnings`, warnings also result in a non-zero status.
+
+use std::env;
+use std::process;
+
+use autogamer::{TileMap, Severity, validate};
+
+const USAGE: &str = "Usage: autogamer-validate [--deny-warnings] <map.tmx>...";
+
+fn main() {
+    let mut deny_warnings = false;
+    let mut paths = Vec::new();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--deny-warnings" => deny_warnings = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            },
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.is_empty() {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+
+    let mut failed = false;
+    for path in &paths {
+        let map = match TileMap::open(path) {
+            Ok(map) => map,
+            Err(err) => {
+                println!("{}: error: {}", path, err);
+                failed = true;
+                continue;
+            },
+        };
+
+        for diagnostic in validate(&map) {
+            println!("{}: {}", path, diagnostic);
+
+            if diagnostic.severity == Severity::Error || deny_warnings {
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
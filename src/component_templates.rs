@@ -4,71 +4,267 @@ mod entity_editor;
 pub use custom_props::*;
 pub use entity_editor::*;
 
+use std::fmt;
+use std::time::Duration;
+
 use thiserror::Error;
 
-use crate::{TileId, Currency, PhysicsCollider};
+use nphysics2d::object::BodyStatus;
+
+use crate::{
+    TileId,
+    TileImages,
+    MotionPaths,
+    ToggleGroups,
+    PathMode,
+    Easing,
+    Image,
+    Sprite,
+    Position,
+    Currency,
+    Item,
+    Door,
+    Damage,
+    Ladder,
+    JumpPad,
+    JumpPadLaunch,
+    FollowPath,
+    Toggle,
+    ToggleTiming,
+    Switch,
+    SignalListener,
+    SignalMessage,
+    PhysicsBody,
+    PhysicsCollider,
+};
+
+/// The number of seconds that an entity cannot be damaged after it is hit, if
+/// not specified with the `invincibility` property
+const DEFAULT_INVINCIBILITY_SECS: f64 = 1.0;
+/// The value that the jump velocity of an entity is multiplied by when it is
+/// launched by a jump pad, if not specified with the `jump_multiplier` property
+const DEFAULT_JUMP_MULTIPLIER: f64 = 2.0;
+/// The number of seconds that a jump pad shows its pressed image for, if not
+/// specified with the `pressed_time` property
+const DEFAULT_PRESSED_SECS: f64 = 0.5;
+/// The speed (in world coordinates per second) that an entity moves along a
+/// path, if not specified with the `path_speed` property
+const DEFAULT_PATH_SPEED: f64 = 100.0;
+/// The number of seconds that an entity stays on for, if not specified with the
+/// `on_time` property
+const DEFAULT_ON_SECS: f64 = 1.0;
+/// The properties that give an entity a `Toggle` component
+///
+/// Entities that only listen for signals are given a `Toggle` by the toggle
+/// system once they receive one.
+const TOGGLE_PROPS: &[&str] = &["on_time", "off_time", "phase", "sync_group", "starts_off", "off_tile"];
+
+/// The part of the map that an entity was created from
+///
+/// Used to identify where a problem came from when a template fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TemplateOrigin {
+    /// A tile in a tile layer
+    Tile(TileId),
+    /// A tile object in an object layer
+    Object {id: u32, tile: TileId},
+}
+
+impl fmt::Display for TemplateOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateOrigin::Tile(id) => write!(f, "tile GID = {}", id),
+            TemplateOrigin::Object {id, tile} => write!(f, "object ID = {}, tile GID = {}", id, tile),
+        }
+    }
+}
+
+impl TemplateOrigin {
+    /// The ID of the tile that the entity was created from
+    pub fn tile(self) -> TileId {
+        match self {
+            TemplateOrigin::Tile(id) |
+            TemplateOrigin::Object {tile: id, ..} => id,
+        }
+    }
+
+    /// The ID of the object that the entity was created from (if any)
+    pub fn object(self) -> Option<u32> {
+        match self {
+            TemplateOrigin::Tile(_) => None,
+            TemplateOrigin::Object {id, ..} => Some(id),
+        }
+    }
+}
 
 #[derive(Debug, Error)]
 pub enum TemplateError {
-    #[error("expected `{prop}` property to have type `{expected_type}` (tile GID = {id})")]
+    #[error("expected `{prop}` property to have type `{expected_type}` ({origin})")]
     TypeError {
-        id: TileId,
+        origin: TemplateOrigin,
         prop: &'static str,
         expected_type: &'static str,
     },
 
-    #[error("expected `{prop}` property to have a value greater than or equal to zero (tile GID = {id})")]
+    #[error("expected `{prop}` property to have a value greater than or equal to zero ({origin})")]
     ExpectedUnsigned {
-        id: TileId,
+        origin: TemplateOrigin,
+        prop: &'static str,
+    },
+
+    #[error("`{prop}` property has invalid value `{value}`, expected {expected} ({origin})")]
+    InvalidValue {
+        origin: TemplateOrigin,
         prop: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+
+    /// An error reported by a template registered outside of this crate
+    #[error("{message} ({origin})")]
+    Custom {
+        origin: TemplateOrigin,
+        message: String,
     },
 
     #[error(transparent)]
     SpecsError(#[from] specs::error::Error),
 }
 
-type Template<P> = for<'a> fn(&EntityEditor<'a>, TileId, &str, &P) -> Result<(), TemplateError>;
+impl TemplateError {
+    /// The part of the map that the entity which caused this error was
+    /// created from (if known)
+    pub fn origin(&self) -> Option<TemplateOrigin> {
+        use TemplateError::*;
+        match *self {
+            TypeError {origin, ..} |
+            ExpectedUnsigned {origin, ..} |
+            InvalidValue {origin, ..} |
+            Custom {origin, ..} => Some(origin),
+            SpecsError(_) => None,
+        }
+    }
+}
 
-/// An extension trait for applying component templates to an entity builder
-/// based on tile type and properties
-pub trait ApplyComponentTemplates {
-    fn apply_templates<P: CustomProps>(
+/// Adds components to an entity based on its tile type and custom properties
+///
+/// Implemented for any function or closure with the same signature as `apply`.
+pub trait ComponentTemplate: Send + Sync {
+    fn apply(
         &self,
-        id: TileId,
+        entity: &EntityEditor,
+        origin: TemplateOrigin,
         tile_type: &str,
-        props: &P,
+        props: &dyn CustomProps,
     ) -> Result<(), TemplateError>;
 }
 
-impl<'a> ApplyComponentTemplates for EntityEditor<'a> {
-    fn apply_templates<P: CustomProps>(
+impl<F> ComponentTemplate for F
+    where F: Fn(&EntityEditor, TemplateOrigin, &str, &dyn CustomProps) -> Result<(), TemplateError> + Send + Sync,
+{
+    fn apply(
         &self,
-        id: TileId,
+        entity: &EntityEditor,
+        origin: TemplateOrigin,
         tile_type: &str,
-        props: &P,
+        props: &dyn CustomProps,
     ) -> Result<(), TemplateError> {
-        // All template functions must be listed here
-        let templates: &[Template<P>] = &[
-            currency,
-            ladder,
-            damage,
-        ];
-
-        for template in templates {
-            template(self, id, tile_type, props)?;
+        self(entity, origin, tile_type, props)
+    }
+}
+
+/// Determines which entities a component template is applied to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TemplateTrigger {
+    /// Applies the template to every entity
+    Always,
+    /// Applies the template to entities with the given tile type
+    TileType(String),
+    /// Applies the template to entities with a custom property of the given
+    /// name (regardless of its value)
+    Property(String),
+}
+
+impl TemplateTrigger {
+    fn matches(&self, tile_type: &str, props: &dyn CustomProps) -> bool {
+        match self {
+            TemplateTrigger::Always => true,
+            TemplateTrigger::TileType(expected) => tile_type == expected,
+            TemplateTrigger::Property(prop) => props.get_prop(prop).is_some(),
+        }
+    }
+}
+
+/// The component templates applied to each entity created from a map
+///
+/// Templates are applied in the order they were registered. The built-in
+/// templates are always registered first.
+pub struct ComponentTemplates {
+    templates: Vec<(TemplateTrigger, Box<dyn ComponentTemplate>)>,
+}
+
+impl fmt::Debug for ComponentTemplates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {templates} = self;
+        f.debug_list()
+            .entries(templates.iter().map(|(trigger, _)| trigger))
+            .finish()
+    }
+}
+
+impl Default for ComponentTemplates {
+    fn default() -> Self {
+        let mut templates = Self {templates: Vec::new()};
+
+        // All built-in template functions must be listed here
+        templates.register(TemplateTrigger::Property("currency_value".to_string()), currency);
+        templates.register(TemplateTrigger::Property("item".to_string()), item);
+        templates.register(TemplateTrigger::TileType("door".to_string()), door);
+        templates.register(TemplateTrigger::TileType("ladder".to_string()), ladder);
+        templates.register(TemplateTrigger::Property("damage".to_string()), damage);
+        templates.register(TemplateTrigger::Property("one_way".to_string()), one_way);
+        templates.register(TemplateTrigger::TileType("jump_pad".to_string()), jump_pad);
+        templates.register(TemplateTrigger::Property("follows_path".to_string()), follows_path);
+        templates.register(TemplateTrigger::Always, toggle);
+        templates.register(TemplateTrigger::Property("listens_to".to_string()), signal_listener);
+        templates.register(TemplateTrigger::TileType("switch".to_string()), switch);
+
+        templates
+    }
+}
+
+impl ComponentTemplates {
+    /// Adds a template that will be applied to every entity that matches the
+    /// given trigger, after all previously registered templates
+    pub fn register<T: ComponentTemplate + 'static>(&mut self, trigger: TemplateTrigger, template: T) {
+        self.templates.push((trigger, Box::new(template)));
+    }
+
+    /// Applies every matching template to the given entity
+    pub fn apply(
+        &self,
+        entity: &EntityEditor,
+        origin: TemplateOrigin,
+        tile_type: &str,
+        props: &dyn CustomProps,
+    ) -> Result<(), TemplateError> {
+        for (trigger, template) in &self.templates {
+            if trigger.matches(tile_type, props) {
+                template.apply(entity, origin, tile_type, props)?;
+            }
         }
 
         Ok(())
     }
 }
 
-fn currency<'a, P: CustomProps>(
-    entity: &EntityEditor<'a>,
-    id: TileId,
+fn currency(
+    entity: &EntityEditor,
+    origin: TemplateOrigin,
     _tile_type: &str,
-    props: &P,
+    props: &dyn CustomProps,
 ) -> Result<(), TemplateError> {
-    if let Some(value) = props.get_i32("currency_value", id) {
+    if let Some(value) = props.get_i32("currency_value", origin) {
         let value = value?;
         entity.add(Currency(value))?;
         // Allow entities to pass through this entity
@@ -78,29 +274,407 @@ fn currency<'a, P: CustomProps>(
     Ok(())
 }
 
-fn ladder<'a, P: CustomProps>(
-    entity: &EntityEditor<'a>,
-    _id: TileId,
+fn item(
+    entity: &EntityEditor,
+    origin: TemplateOrigin,
+    _tile_type: &str,
+    props: &dyn CustomProps,
+) -> Result<(), TemplateError> {
+    if let Some(name) = props.get_str("item", origin) {
+        let name = name?.to_string();
+        let count = props.get_u32("item_count", origin).transpose()?
+            .unwrap_or(1);
+        entity.add(Item {name, count})?;
+        // Allow entities to pass through this entity
+        make_sensor(entity);
+    }
+
+    Ok(())
+}
+
+fn door(
+    entity: &EntityEditor,
+    origin: TemplateOrigin,
     tile_type: &str,
-    _props: &P,
+    props: &dyn CustomProps,
+) -> Result<(), TemplateError> {
+    if tile_type != "door" {
+        return Ok(());
+    }
+
+    let required_item = props.get_str("required_item", origin).transpose()?
+        .map(|name| name.to_string());
+    let consume_item = props.get_bool("consume_item", origin).transpose()?
+        .unwrap_or(false);
+    let open_image = match props.get_u32("open_tile", origin).transpose()? {
+        Some(local_id) => Some(tileset_tile_image(entity, origin, "open_tile", local_id)?),
+        None => None,
+    };
+
+    entity.add(Door {required_item, consume_item, open_image, open: false})?;
+
+    Ok(())
+}
+
+fn ladder(
+    entity: &EntityEditor,
+    _origin: TemplateOrigin,
+    tile_type: &str,
+    _props: &dyn CustomProps,
 ) -> Result<(), TemplateError> {
     if tile_type == "ladder" {
+        entity.add(Ladder)?;
+        // Allow entities to pass through the ladder when they aren't climbing
         make_sensor(entity);
     }
 
     Ok(())
 }
 
-fn damage<'a, P: CustomProps>(
-    entity: &EntityEditor<'a>,
-    id: TileId,
+fn damage(
+    entity: &EntityEditor,
+    origin: TemplateOrigin,
+    _tile_type: &str,
+    props: &dyn CustomProps,
+) -> Result<(), TemplateError> {
+    if let Some(amount) = props.get_u32("damage", origin) {
+        let amount = amount?;
+        let knockback = props.get_f64("knockback", origin).transpose()?
+            .unwrap_or(0.0);
+        let invincibility = duration_prop(props, origin, "invincibility")?
+            .unwrap_or_else(|| Duration::from_secs_f64(DEFAULT_INVINCIBILITY_SECS));
+
+        entity.add(Damage {amount, knockback, invincibility})?;
+    }
+
+    Ok(())
+}
+
+fn one_way(
+    entity: &EntityEditor,
+    origin: TemplateOrigin,
+    _tile_type: &str,
+    props: &dyn CustomProps,
+) -> Result<(), TemplateError> {
+    if let Some(one_way) = props.get_bool("one_way", origin) {
+        if one_way? {
+            let mut collider = entity.get_mut::<PhysicsCollider>()
+                .expect("bug: all tiles should have a physics collider component");
+            collider.one_way = true;
+        }
+    }
+
+    Ok(())
+}
+
+fn jump_pad(
+    entity: &EntityEditor,
+    origin: TemplateOrigin,
+    tile_type: &str,
+    props: &dyn CustomProps,
+) -> Result<(), TemplateError> {
+    if tile_type != "jump_pad" {
+        return Ok(());
+    }
+
+    let launch = match props.get_f64("launch_speed", origin).transpose()? {
+        Some(speed) => JumpPadLaunch::Speed(speed),
+        None => JumpPadLaunch::JumpMultiplier(props.get_f64("jump_multiplier", origin).transpose()?
+            .unwrap_or(DEFAULT_JUMP_MULTIPLIER)),
+    };
+
+    let pressed_image = match props.get_u32("pressed_tile", origin).transpose()? {
+        Some(local_id) => Some(tileset_tile_image(entity, origin, "pressed_tile", local_id)?),
+        None => None,
+    };
+
+    let pressed_duration = duration_prop(props, origin, "pressed_time")?
+        .unwrap_or_else(|| Duration::from_secs_f64(DEFAULT_PRESSED_SECS));
+
+    entity.add(JumpPad {launch, pressed_image, pressed_duration})?;
+
+    Ok(())
+}
+
+fn follows_path(
+    entity: &EntityEditor,
+    origin: TemplateOrigin,
+    _tile_type: &str,
+    props: &dyn CustomProps,
+) -> Result<(), TemplateError> {
+    let path_id = match props.get_u32("follows_path", origin).transpose()? {
+        // Object properties are stored as the ID of the object, with 0 meaning
+        // that no object was selected
+        Some(0) | None => return Ok(()),
+        Some(path_id) => path_id,
+    };
+
+    let path = entity.resource::<MotionPaths>()
+        .expect("bug: paths should be added before any templates are applied")
+        .0.get(&path_id)
+        .cloned()
+        .ok_or_else(|| TemplateError::InvalidValue {
+            origin,
+            prop: "follows_path",
+            value: path_id.to_string(),
+            expected: "the ID of an object with the `path` type",
+        })?;
+
+    let speed = props.get_f64("path_speed", origin).transpose()?
+        .unwrap_or(DEFAULT_PATH_SPEED);
+    if speed < 0.0 {
+        return Err(TemplateError::InvalidValue {
+            origin,
+            prop: "path_speed",
+            value: speed.to_string(),
+            expected: "a speed greater than or equal to zero",
+        });
+    }
+
+    let mode = match props.get_str("path_mode", origin).transpose()? {
+        None | Some("ping_pong") => PathMode::PingPong,
+        Some("loop") => PathMode::Loop,
+        Some(value) => return Err(TemplateError::InvalidValue {
+            origin,
+            prop: "path_mode",
+            value: value.to_string(),
+            expected: "`ping_pong` or `loop`",
+        }),
+    };
+
+    let easing = match props.get_str("path_easing", origin).transpose()? {
+        None | Some("linear") => Easing::Linear,
+        Some("ease_in") => Easing::EaseIn,
+        Some("ease_out") => Easing::EaseOut,
+        Some("ease_in_out") => Easing::EaseInOut,
+        Some(value) => return Err(TemplateError::InvalidValue {
+            origin,
+            prop: "path_easing",
+            value: value.to_string(),
+            expected: "`linear`, `ease_in`, `ease_out` or `ease_in_out`",
+        }),
+    };
+
+    // The entity moves in the shape of the path, starting from its current
+    // position
+    let Position(pos) = *entity.get::<Position>()
+        .expect("bug: all tiles should have a position component");
+    let offset = pos - path.points()[0];
+
+    entity.add(FollowPath {path, offset, speed, mode, easing})?;
+    // Kinematic bodies are moved without being affected by gravity or pushed
+    // around by other bodies
+    entity.add(PhysicsBody {
+        gravity_enabled: false,
+        body_status: BodyStatus::Kinematic,
+        ..PhysicsBody::default()
+    })?;
+
+    Ok(())
+}
+
+fn toggle(
+    entity: &EntityEditor,
+    origin: TemplateOrigin,
+    _tile_type: &str,
+    props: &dyn CustomProps,
+) -> Result<(), TemplateError> {
+    if TOGGLE_PROPS.iter().all(|&prop| props.get_prop(prop).is_none()) {
+        return Ok(());
+    }
+
+    let on_duration = duration_prop(props, origin, "on_time")?;
+    let off_duration = duration_prop(props, origin, "off_time")?;
+    let phase = duration_prop(props, origin, "phase")?;
+    let sync_group = props.get_str("sync_group", origin).transpose()?;
+
+    let mut timing = ToggleTiming {
+        phase: phase.unwrap_or_default(),
+        ..default_toggle_timing(on_duration, off_duration)
+    };
+
+    if let Some(group) = sync_group {
+        let mut groups = entity.resource_mut::<ToggleGroups>()
+            .expect("bug: toggle groups should be added before any templates are applied");
+
+        match groups.0.get(group) {
+            // Every entity in the group uses the timing of the first entity
+            // that was added to it
+            Some(&group_timing) => {
+                let ToggleTiming {
+                    on_duration: group_on_duration,
+                    off_duration: group_off_duration,
+                    phase: group_phase,
+                } = group_timing;
+                let specified = [
+                    ("on_time", on_duration, group_on_duration),
+                    ("off_time", off_duration, group_off_duration),
+                    ("phase", phase, group_phase),
+                ];
+
+                for &(prop, value, group_value) in &specified {
+                    match value {
+                        Some(value) if value != group_value => return Err(TemplateError::InvalidValue {
+                            origin,
+                            prop,
+                            value: value.as_secs_f64().to_string(),
+                            expected: "the same value as every other entity with the same `sync_group`",
+                        }),
+                        _ => {},
+                    }
+                }
+
+                timing = group_timing;
+            },
+
+            None => {
+                groups.0.insert(group.to_string(), timing);
+            },
+        }
+    }
+
+    // Entities that only listen for signals stay on until they are switched
+    // off
+    let is_timed = on_duration.is_some() || off_duration.is_some() || phase.is_some() || sync_group.is_some();
+    let timing = if is_timed { Some(timing) } else { None };
+
+    let switched_on = !props.get_bool("starts_off", origin).transpose()?.unwrap_or(false);
+
+    let off_image = match props.get_u32("off_tile", origin).transpose()? {
+        Some(local_id) => Some(tileset_tile_image(entity, origin, "off_tile", local_id)?),
+        None => None,
+    };
+
+    entity.add(Toggle {timing, switched_on, off_image})?;
+
+    Ok(())
+}
+
+fn signal_listener(
+    entity: &EntityEditor,
+    origin: TemplateOrigin,
+    _tile_type: &str,
+    props: &dyn CustomProps,
+) -> Result<(), TemplateError> {
+    let signal = match props.get_str("listens_to", origin) {
+        Some(signal) => signal?,
+        None => return Ok(()),
+    };
+
+    entity.add(SignalListener {signal: signal.to_string()})?;
+
+    Ok(())
+}
+
+fn switch(
+    entity: &EntityEditor,
+    origin: TemplateOrigin,
     tile_type: &str,
-    props: &P,
+    props: &dyn CustomProps,
 ) -> Result<(), TemplateError> {
-    //TODO
+    if tile_type != "switch" {
+        return Ok(());
+    }
+
+    let signal = props.get_str("signal", origin).transpose()?
+        .map(|signal| signal.to_string());
+    // Object properties are stored as the ID of the object, with 0 meaning
+    // that no object was selected
+    let target = props.get_u32("target", origin).transpose()?
+        .filter(|&target| target != 0);
+
+    let message = match props.get_str("message", origin).transpose()? {
+        None | Some("toggle") => SignalMessage::Toggle,
+        Some("on") => SignalMessage::On,
+        Some("off") => SignalMessage::Off,
+        Some(value) => return Err(TemplateError::InvalidValue {
+            origin,
+            prop: "message",
+            value: value.to_string(),
+            expected: "`toggle`, `on` or `off`",
+        }),
+    };
+
+    let switched_image = match props.get_u32("switched_tile", origin).transpose()? {
+        Some(local_id) => Some(tileset_tile_image(entity, origin, "switched_tile", local_id)?),
+        None => None,
+    };
+
+    entity.add(Switch {signal, target, message, switched_image})?;
+    // Switches are activated by overlapping them, so they shouldn't stop
+    // anything from moving
+    make_sensor(entity);
+
     Ok(())
 }
 
+/// Returns the timing used for the given durations if no other timing is
+/// specified
+///
+/// An entity stays off for the same amount of time that it stays on for by
+/// default.
+fn default_toggle_timing(on_duration: Option<Duration>, off_duration: Option<Duration>) -> ToggleTiming {
+    let on_duration = on_duration.unwrap_or_else(|| Duration::from_secs_f64(DEFAULT_ON_SECS));
+    ToggleTiming {
+        on_duration,
+        off_duration: off_duration.unwrap_or(on_duration),
+        phase: Duration::default(),
+    }
+}
+
+/// Gets a float property that represents a number of seconds
+fn duration_prop(
+    props: &dyn CustomProps,
+    origin: TemplateOrigin,
+    prop: &'static str,
+) -> Result<Option<Duration>, TemplateError> {
+    let secs = match props.get_f64(prop, origin).transpose()? {
+        Some(secs) => secs,
+        None => return Ok(None),
+    };
+
+    // Rejects negative numbers, NaN, infinity and anything too large to be
+    // stored in a `Duration`
+    Duration::try_from_secs_f64(secs).map(Some).map_err(|_| TemplateError::InvalidValue {
+        origin,
+        prop,
+        value: secs.to_string(),
+        expected: "a number of seconds greater than or equal to zero",
+    })
+}
+
+/// Returns the current image of the entity's sprite, changed to show the tile
+/// with the given local ID from the same tileset as the entity's tile
+///
+/// Any error refers to the given property.
+fn tileset_tile_image(
+    entity: &EntityEditor,
+    origin: TemplateOrigin,
+    prop: &'static str,
+    local_id: u32,
+) -> Result<Image, TemplateError> {
+    let invalid_tile = || TemplateError::InvalidValue {
+        origin,
+        prop,
+        value: local_id.to_string(),
+        expected: "the ID of a tile in the same tileset",
+    };
+
+    let tile_images = entity.resource::<TileImages>()
+        .expect("bug: tile images should be added before any templates are applied");
+    let tile_image = tile_images.tileset_tile(origin.tile(), local_id)
+        .and_then(|id| tile_images.get(id))
+        .ok_or_else(invalid_tile)?;
+
+    let sprite = entity.get::<Sprite>()
+        .expect("bug: all tiles should have a sprite component");
+    Ok(Image {
+        id: tile_image.id,
+        src: tile_image.src,
+        ..sprite.image.clone()
+    })
+}
+
 /// Retrieves the physics collider component of the given entity and makes it
 /// into a sensor. A sensor will not generate contact events, but will generate
 /// proximity events. That means that you can interact with it, but it won't
@@ -2,37 +2,131 @@ use std::hash::Hash;
 use std::collections::HashMap;
 use std::borrow::Borrow;
 use std::convert::TryInto;
+use std::path::{Path, PathBuf};
 
-use tiled::PropertyValue;
+use sdl2::pixels::Color;
 
-use crate::TileId;
+pub use tiled::PropertyValue;
 
-use super::TemplateError;
+use super::{TemplateError, TemplateOrigin};
 
 /// Provides convenient access to custom property values, including common type
 /// conversions
 pub trait CustomProps {
-    fn get_prop(&self, prop: &'static str) -> Option<&PropertyValue>;
+    fn get_prop(&self, prop: &str) -> Option<&PropertyValue>;
 
-    fn get_i32(&self, prop: &'static str, id: TileId) -> Option<Result<i32, TemplateError>> {
+    /// Iterates over the name and value of every property
+    ///
+    /// Each property name is yielded at most once.
+    fn iter_props(&self) -> Box<dyn Iterator<Item=(&str, &PropertyValue)> + '_>;
+
+    /// The directory that the paths in file properties are relative to
+    fn base_dir(&self) -> &Path {
+        Path::new("")
+    }
+
+    fn get_bool(&self, prop: &'static str, origin: TemplateOrigin) -> Option<Result<bool, TemplateError>> {
+        self.get_prop(prop).map(|value| match value {
+            &PropertyValue::BoolValue(value) => Ok(value),
+            _ => Err(TemplateError::TypeError {origin, prop, expected_type: "bool"}),
+        })
+    }
+
+    fn get_i32(&self, prop: &'static str, origin: TemplateOrigin) -> Option<Result<i32, TemplateError>> {
         self.get_prop(prop).map(|value| match value {
             &PropertyValue::IntValue(value) => Ok(value),
-            _ => Err(TemplateError::TypeError {id, prop, expected_type: "int"}),
+            _ => Err(TemplateError::TypeError {origin, prop, expected_type: "int"}),
         })
     }
 
-    fn get_u32(&self, prop: &'static str, id: TileId) -> Option<Result<u32, TemplateError>> {
-        let value = self.get_i32(prop, id)?;
+    fn get_u32(&self, prop: &'static str, origin: TemplateOrigin) -> Option<Result<u32, TemplateError>> {
+        let value = self.get_i32(prop, origin)?;
         Some(value.and_then(|value| value.try_into().map_err(|_| {
-            TemplateError::ExpectedUnsigned {id, prop}
+            TemplateError::ExpectedUnsigned {origin, prop}
         })))
     }
+
+    /// Gets a float property, also allowing int values
+    fn get_f64(&self, prop: &'static str, origin: TemplateOrigin) -> Option<Result<f64, TemplateError>> {
+        self.get_prop(prop).map(|value| match value {
+            &PropertyValue::FloatValue(value) => Ok(value as f64),
+            &PropertyValue::IntValue(value) => Ok(value as f64),
+            _ => Err(TemplateError::TypeError {origin, prop, expected_type: "float"}),
+        })
+    }
+
+    fn get_str(&self, prop: &'static str, origin: TemplateOrigin) -> Option<Result<&str, TemplateError>> {
+        self.get_prop(prop).map(|value| match value {
+            PropertyValue::StringValue(value) => Ok(value.as_str()),
+            _ => Err(TemplateError::TypeError {origin, prop, expected_type: "string"}),
+        })
+    }
+
+    /// Gets a color property, also allowing string values in the `#RRGGBB` or
+    /// `#AARRGGBB` format
+    fn get_color(&self, prop: &'static str, origin: TemplateOrigin) -> Option<Result<Color, TemplateError>> {
+        self.get_prop(prop).map(|value| match value {
+            &PropertyValue::ColorValue(argb) => Ok(argb_to_color(argb)),
+            PropertyValue::StringValue(value) => parse_color(value).ok_or_else(|| {
+                TemplateError::InvalidValue {
+                    origin,
+                    prop,
+                    value: value.clone(),
+                    expected: "a color in the format `#RRGGBB` or `#AARRGGBB`",
+                }
+            }),
+            _ => Err(TemplateError::TypeError {origin, prop, expected_type: "color"}),
+        })
+    }
+
+    /// Gets a file property, resolving the path relative to `base_dir()`
+    ///
+    /// String values are also allowed since older versions of Tiled did not
+    /// support file properties.
+    fn get_file(&self, prop: &'static str, origin: TemplateOrigin) -> Option<Result<PathBuf, TemplateError>> {
+        self.get_prop(prop).map(|value| match value {
+            PropertyValue::FileValue(path) |
+            PropertyValue::StringValue(path) => if path.is_empty() {
+                Err(TemplateError::InvalidValue {
+                    origin,
+                    prop,
+                    value: path.clone(),
+                    expected: "a non-empty file path",
+                })
+            } else {
+                // Joining with an absolute path produces the absolute path as-is
+                Ok(self.base_dir().join(path))
+            },
+            _ => Err(TemplateError::TypeError {origin, prop, expected_type: "file"}),
+        })
+    }
+}
+
+/// Converts a color stored by Tiled as `0xAARRGGBB`
+fn argb_to_color(argb: u32) -> Color {
+    let [a, r, g, b] = argb.to_be_bytes();
+    Color::RGBA(r, g, b, a)
+}
+
+/// Parses a color in the format `#RRGGBB` or `#AARRGGBB`
+fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    let argb = u32::from_str_radix(hex, 16).ok()?;
+    match hex.len() {
+        6 => Some(argb_to_color(0xFF000000 | argb)),
+        8 => Some(argb_to_color(argb)),
+        _ => None,
+    }
 }
 
 impl CustomProps for HashMap<String, PropertyValue> {
-    fn get_prop(&self, prop: &'static str) -> Option<&PropertyValue> {
+    fn get_prop(&self, prop: &str) -> Option<&PropertyValue> {
         self.get(prop)
     }
+
+    fn iter_props(&self) -> Box<dyn Iterator<Item=(&str, &PropertyValue)> + '_> {
+        Box::new(self.iter().map(|(prop, value)| (prop.as_str(), value)))
+    }
 }
 
 pub(crate) struct JointHashMap<'a, K, V> {
@@ -54,7 +148,35 @@ impl<'a, K, V> JointHashMap<'a, K, V> where
 }
 
 impl<'a> CustomProps for JointHashMap<'a, String, PropertyValue> {
-    fn get_prop(&self, prop: &'static str) -> Option<&PropertyValue> {
+    fn get_prop(&self, prop: &str) -> Option<&PropertyValue> {
         self.get(prop)
     }
+
+    fn iter_props(&self) -> Box<dyn Iterator<Item=(&str, &PropertyValue)> + '_> {
+        // Properties in `base` are hidden by properties with the same name in
+        // `data`
+        let base = self.base.iter().filter(move |(prop, _)| !self.data.contains_key(*prop));
+        Box::new(self.data.iter().chain(base).map(|(prop, value)| (prop.as_str(), value)))
+    }
+}
+
+/// Custom properties from a map, along with the directory containing that map
+pub(crate) struct MapProps<'a, P> {
+    pub props: &'a P,
+    /// The directory containing the map, within the asset source of the map
+    pub base_dir: &'a Path,
+}
+
+impl<'a, P: CustomProps> CustomProps for MapProps<'a, P> {
+    fn get_prop(&self, prop: &str) -> Option<&PropertyValue> {
+        self.props.get_prop(prop)
+    }
+
+    fn iter_props(&self) -> Box<dyn Iterator<Item=(&str, &PropertyValue)> + '_> {
+        self.props.iter_props()
+    }
+
+    fn base_dir(&self) -> &Path {
+        self.base_dir
+    }
 }
@@ -1,6 +1,6 @@
 use std::ops::{Deref, DerefMut};
 
-use specs::{World, Entity, Component, WorldExt, ReadStorage, WriteStorage, storage::{InsertResult, GenericWriteStorage}};
+use specs::{World, Entity, Component, WorldExt, ReadStorage, WriteStorage, shred::{Fetch, FetchMut, Resource}, storage::{InsertResult, GenericWriteStorage}};
 
 /// Provides read and write access to the components of a single entity
 pub struct EntityEditor<'a> {
@@ -13,6 +13,11 @@ impl<'a> EntityEditor<'a> {
         Self {world, entity}
     }
 
+    /// The entity being edited
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
     /// Adds a component to this entity
     pub fn add<C: Component>(&self, component: C) -> InsertResult<C> {
         self.world.write_component().insert(self.entity, component)
@@ -65,6 +70,18 @@ impl<'a> EntityEditor<'a> {
             entity: self.entity,
         }
     }
+
+    /// Gets an immutable reference to the given resource or returns `None` if
+    /// that resource has not been added to the world
+    pub fn resource<R: Resource>(&self) -> Option<Fetch<R>> {
+        self.world.try_fetch()
+    }
+
+    /// Gets a mutable reference to the given resource or returns `None` if
+    /// that resource has not been added to the world
+    pub fn resource_mut<R: Resource>(&self) -> Option<FetchMut<R>> {
+        self.world.try_fetch_mut()
+    }
 }
 
 /// Provides immutable access to a component through the `Deref` trait
@@ -1,3 +1,8 @@
+use std::sync::Arc;
+use std::time::Duration;
+use std::ops::Range;
+use std::collections::HashMap;
+
 use specs::{World, WorldExt, Component, VecStorage, HashMapStorage, FlaggedStorage, NullStorage};
 use nphysics2d::{
     math::ForceType,
@@ -22,6 +27,11 @@ use crate::{
     Collider,
     ShapeRect,
     Key,
+    Animation,
+    MotionPath,
+    PathMode,
+    Easing,
+    SignalMessage,
 };
 
 pub use nphysics2d::ncollide2d::pipeline::CollisionGroups;
@@ -40,12 +50,32 @@ components! {
     PhysicsBody,
     PhysicsCollider,
     Sprite,
+    AnimatedSprite,
+    DrawLayer,
     CharacterSprites,
     PlatformerControls,
+    Ladder,
+    Climbing,
+    DroppingDown,
+    Interacting,
     Health,
     ViewportTarget,
     Wallet,
     Currency,
+    Inventory,
+    Item,
+    Door,
+    Damage,
+    Invincible,
+    KnockedBack,
+    JumpPad,
+    JumpPadPressed,
+    FollowPath,
+    Carried,
+    Toggle,
+    ToggledOff,
+    Switch,
+    SignalListener,
 }
 
 /// A marker component given to an entity to indicate that it represents one of
@@ -199,14 +229,35 @@ pub struct PhysicsCollider {
     ///
     /// If there is no parent body, this field is ignored
     pub offset: Vec2,
+    /// The angle (in radians) by which to rotate this collider about its
+    /// position
+    ///
+    /// Since the y-axis points down, positive angles rotate the collider
+    /// clockwise.
+    pub rotation: f64,
     /// Updating this after the component is initially added is not supported
     pub density: f64,
     /// Updating this after the component is initially added is not supported
     pub material: BasicMaterial,
     pub margin: f64,
+    /// The collision groups that this collider is a member of, whitelists and
+    /// blacklists
+    ///
+    /// The groups in `PhysicsCollider::ONE_WAY_BODY_GROUPS` are reserved. The
+    /// physics system overwrites the membership of dynamic bodies and the
+    /// blacklist of one-way colliders in those groups.
     pub collision_groups: CollisionGroups,
     /// Updating this after the component is initially added is not supported
     pub sensor: bool,
+    /// If true, bodies can pass through this collider from below and land on
+    /// top of it (e.g. a platform that can be jumped through)
+    ///
+    /// Contacts with this collider are ignored while a body is moving upward,
+    /// is below the top surface of the collider, or has a `DroppingDown`
+    /// component.
+    ///
+    /// Updating this after the component is initially added is not supported
+    pub one_way: bool,
 }
 
 impl Default for PhysicsCollider {
@@ -215,11 +266,13 @@ impl Default for PhysicsCollider {
             handle: Default::default(),
             shape: Shape::Rect(ShapeRect::new(Vec2::new(0.0, 0.0))),
             offset: Default::default(),
+            rotation: Default::default(),
             density: Default::default(),
             material: Default::default(),
             margin: 0.01,
             collision_groups: Default::default(),
             sensor: Default::default(),
+            one_way: Default::default(),
         }
     }
 }
@@ -229,6 +282,19 @@ impl PhysicsCollider {
     pub const GROUND_COLLISION_GROUP: usize = 0;
     pub const PLAYER_COLLISION_GROUP: usize = 1;
     pub const ENEMY_COLLISION_GROUP: usize = 2;
+    /// Collision group that every one-way collider is a member of
+    ///
+    /// Colliders with this group in their blacklist are never stopped by
+    /// one-way colliders.
+    pub const ONE_WAY_COLLISION_GROUP: usize = 3;
+    /// Collision groups reserved for the physics system to decide which bodies
+    /// pass through each one-way collider
+    ///
+    /// Every dynamic body is made a member of one of these groups when it is
+    /// first seen, and stays in that group while it exists. Each one-way
+    /// collider blacklists the groups of the bodies
+    /// passing through it. Any changes made to these groups are overwritten.
+    pub const ONE_WAY_BODY_GROUPS: Range<usize> = 4..30;
 
     pub fn ground_collision_groups() -> CollisionGroups {
         CollisionGroups::new()
@@ -253,15 +319,21 @@ impl PhysicsCollider {
             handle: _,
             ref shape,
             offset,
+            rotation,
             density,
             material,
             margin,
-            collision_groups,
+            mut collision_groups,
             sensor,
+            one_way,
         } = *self;
 
+        if one_way {
+            collision_groups.modify_membership(Self::ONE_WAY_COLLISION_GROUP, true);
+        }
+
         ColliderDesc::new(shape.to_handle())
-            .position(Isometry::new(base_pos + offset, 0.0))
+            .position(Isometry::new(base_pos + offset, rotation))
             .density(density)
             .material(MaterialHandle::new(material))
             .margin(margin)
@@ -276,6 +348,7 @@ impl PhysicsCollider {
             // shape primitives do not implement PartialEq
             shape: _,
             offset,
+            rotation,
             density,
             // Updating the material is not supported and checking if it changed
             // isn't easy because BasicMaterial doesn't implement PartialEq
@@ -285,14 +358,18 @@ impl PhysicsCollider {
             // groups type does not implement PartialEq
             collision_groups: _,
             sensor,
+            // Updating this is not supported because it is stored in the
+            // collision groups of the collider
+            one_way: _,
         } = *self;
 
         // Need to check each property first so we don't invalidate caches when
         // there is no update
 
         let pos = base_pos + offset;
-        if collider.position().translation.vector != pos {
-            collider.set_position(Isometry::new(pos, 0.0));
+        let collider_pos = collider.position();
+        if collider_pos.translation.vector != pos || collider_pos.rotation.angle() != rotation {
+            collider.set_position(Isometry::new(pos, rotation));
         }
 
         // No way to update the density currently in nphysics API
@@ -333,10 +410,31 @@ pub struct Sprite {
     pub draw_order: u8,
 }
 
-#[derive(Component, Debug, Clone, PartialEq)]
+/// Updates the image of an entity's `Sprite` to the current frame of the given
+/// animation
+///
+/// All entities with the same animation will display the same frame at the
+/// same time.
+#[derive(Component, Debug, Clone)]
+#[storage(VecStorage)]
+pub struct AnimatedSprite(pub Arc<Animation>);
+
+/// The layer of the map that an entity's `Sprite` is drawn with
+///
+/// The value is the index of a layer in Tiled. The sprite is drawn in front of
+/// every layer with a lower index and behind every layer with a higher index.
+/// Sprites in the same layer are ordered by their draw order. Entities without
+/// this component are drawn with the first tile layer that contains entities.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[storage(VecStorage)]
+pub struct DrawLayer(pub u32);
+
+#[derive(Component, Debug, Clone)]
 #[storage(HashMapStorage)]
 pub struct CharacterSprites {
     pub idle: Option<Sprite>,
+    /// The animation played while the entity is climbing
+    pub climb: Option<Arc<Animation>>,
 }
 
 impl CharacterSprites {
@@ -356,6 +454,22 @@ pub struct PlatformerControls {
     pub right_key: Key,
     /// The key used to make the entity initiate a jump (default: spacebar)
     pub jump_key: Key,
+    /// The key used to climb up a ladder (default: up arrow key)
+    pub climb_up_key: Key,
+    /// The key used to climb down a ladder (default: down arrow key)
+    pub climb_down_key: Key,
+    /// The key used to drop down through a one-way platform (default: down
+    /// arrow key)
+    ///
+    /// This is the same key as `climb_down_key` by default, so that an entity
+    /// climbing down a ladder also drops through any one-way platforms along
+    /// the way.
+    pub drop_down_key: Key,
+    /// The key used to interact with things like switches (default: E key)
+    ///
+    /// Kept separate from the climb up key by default so that a switch next to
+    /// a ladder can still be climbed past.
+    pub interact_key: Key,
 
     /// The velocity applied on the x-axis when the left or right arrow keys are
     /// pressed. The value will be negated for the left arrow key to allow it to
@@ -376,6 +490,12 @@ pub struct PlatformerControls {
     /// touching the ground. Used to change the affect of the left and right
     /// keys while an entity is in the air.
     pub midair_horizontal_multiplier: f64,
+    /// The speed at which the entity moves along the y-axis while climbing a
+    /// ladder. The value will be negated for the climb up key since the y-axis
+    /// goes down.
+    ///
+    /// The entity cannot climb ladders if this is zero.
+    pub climb_velocity: f64,
 }
 
 impl Default for PlatformerControls {
@@ -384,13 +504,47 @@ impl Default for PlatformerControls {
             left_key: Key::Left,
             right_key: Key::Right,
             jump_key: Key::Space,
+            climb_up_key: Key::Up,
+            climb_down_key: Key::Down,
+            drop_down_key: Key::Down,
+            interact_key: Key::E,
             horizontal_velocity: 0.0,
             jump_velocity: 0.0,
             midair_horizontal_multiplier: 1.0,
+            climb_velocity: 0.0,
         }
     }
 }
 
+/// A marker component for entities that can be climbed by entities with a
+/// `PlatformerControls` component
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[storage(NullStorage)]
+pub struct Ladder;
+
+/// Added to an entity with a `PlatformerControls` component while it is
+/// climbing a ladder. Gravity is disabled for the entity while it is climbing.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[storage(HashMapStorage)]
+pub struct Climbing {
+    /// Whether gravity was enabled for the entity before it started climbing,
+    /// so it can be restored once the entity stops climbing
+    pub gravity_enabled: bool,
+}
+
+/// Added to an entity with a `PlatformerControls` component while its drop
+/// down key is pressed. The entity falls through any one-way colliders while
+/// it has this component.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[storage(NullStorage)]
+pub struct DroppingDown;
+
+/// Added to an entity with a `PlatformerControls` component during the frame
+/// in which its interact key is pressed
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[storage(NullStorage)]
+pub struct Interacting;
+
 /// The health of an entity
 #[derive(Component, Debug, Clone, PartialEq)]
 #[storage(HashMapStorage)]
@@ -427,3 +581,332 @@ pub struct Wallet(pub i32);
 #[derive(Component, Debug, Clone, PartialEq)]
 #[storage(HashMapStorage)]
 pub struct Currency(pub i32);
+
+/// The number of each kind of item collected by this entity so far, indexed by
+/// the name of the item
+///
+/// This component must be present for an entity to be able to pick up entities
+/// that have `Item` components or open entities that have `Door` components.
+#[derive(Component, Debug, Default, Clone, PartialEq)]
+#[storage(HashMapStorage)]
+pub struct Inventory(pub HashMap<String, u32>);
+
+impl Inventory {
+    /// Returns the number of items with the given name in this inventory
+    pub fn count(&self, name: &str) -> u32 {
+        let Inventory(items) = self;
+        items.get(name).copied().unwrap_or(0)
+    }
+
+    /// Adds the given number of items with the given name to this inventory
+    pub fn add(&mut self, name: &str, count: u32) {
+        let Inventory(items) = self;
+        let item_count = items.entry(name.to_string()).or_insert(0);
+        *item_count = item_count.saturating_add(count);
+    }
+
+    /// Removes the given number of items with the given name from this
+    /// inventory
+    ///
+    /// Returns false and leaves the inventory unchanged if there are not enough
+    /// items to remove.
+    pub fn remove(&mut self, name: &str, count: u32) -> bool {
+        let Inventory(items) = self;
+        match items.get_mut(name) {
+            Some(item_count) if *item_count >= count => {
+                *item_count -= count;
+                if *item_count == 0 {
+                    items.remove(name);
+                }
+                true
+            },
+
+            _ => count == 0,
+        }
+    }
+}
+
+/// If an entity with this component collides with an entity that has an
+/// `Inventory` component, the given number of items with the given name will be
+/// added to the inventory and the entity with this item component will be
+/// removed.
+///
+/// Note that the entity must have some collision geometry in order for
+/// collisions to be detected.
+#[derive(Component, Debug, Clone, PartialEq)]
+#[storage(HashMapStorage)]
+pub struct Item {
+    /// The name of the item, used to identify it in an `Inventory`
+    pub name: String,
+    /// The number of items added to an inventory when this entity is picked up
+    pub count: u32,
+}
+
+/// An entity with this component stops other entities until it is touched by
+/// an entity with the required item in its `Inventory` component
+///
+/// When the door opens, its `PhysicsCollider` becomes a sensor so that other
+/// entities can pass through it.
+#[derive(Component, Debug, Clone, PartialEq)]
+#[storage(HashMapStorage)]
+pub struct Door {
+    /// The name of the item needed to open the door
+    ///
+    /// If `None`, the door opens when it is touched by any entity with an
+    /// `Inventory` component.
+    pub required_item: Option<String>,
+    /// If true, one of the required item is removed from the inventory of the
+    /// entity that opens the door
+    pub consume_item: bool,
+    /// The image that the `Sprite` of this entity switches to when the door
+    /// opens (if any)
+    pub open_image: Option<Image>,
+    /// true once the door has been opened
+    pub open: bool,
+}
+
+/// If an entity with a `Health` component touches or intersects with an entity
+/// that has this component, its health will be reduced by the given amount.
+///
+/// Note that the entity must have some collision geometry in order for
+/// collisions to be detected.
+#[derive(Component, Debug, Clone, PartialEq)]
+#[storage(HashMapStorage)]
+pub struct Damage {
+    /// The amount subtracted from the health of an entity each time it is hit
+    pub amount: u32,
+    /// The speed (in world coordinates per second) that an entity is pushed
+    /// away from this entity at when it is hit
+    ///
+    /// The velocity of the entity is replaced, so the entity is pushed the same
+    /// distance no matter how it was moving before. Only entities with a
+    /// `PhysicsBody` component are pushed.
+    pub knockback: f64,
+    /// The amount of time after being hit that an entity cannot be hit again
+    pub invincibility: Duration,
+}
+
+/// An entity with this component cannot be damaged until the level clock
+/// reaches the given time.
+///
+/// This component is added automatically when an entity is hit by something
+/// with a `Damage` component and removed once the time has passed.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+#[storage(HashMapStorage)]
+pub struct Invincible {
+    /// The time (since the level started) at which the entity can be damaged
+    /// again
+    pub until: Duration,
+}
+
+/// Added to an entity when it is pushed away by something with a `Damage`
+/// component. The keys of its `PlatformerControls` (if any) do not change its
+/// horizontal velocity until the level clock reaches the given time.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+#[storage(HashMapStorage)]
+pub struct KnockedBack {
+    /// The time (since the level started) at which the entity can be
+    /// controlled again
+    pub until: Duration,
+}
+
+/// Launches entities upward when they land on top of an entity with this
+/// component
+///
+/// Only entities with a `PhysicsBody` component can be launched.
+#[derive(Component, Debug, Clone, PartialEq)]
+#[storage(HashMapStorage)]
+pub struct JumpPad {
+    /// Determines the velocity that entities are launched with
+    pub launch: JumpPadLaunch,
+    /// The image that the `Sprite` of this entity switches to after it
+    /// launches something (if any)
+    pub pressed_image: Option<Image>,
+    /// The amount of time that the pressed image is shown for
+    pub pressed_duration: Duration,
+}
+
+/// Determines the velocity that a `JumpPad` launches entities with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JumpPadLaunch {
+    /// Launches entities upward at the given speed
+    Speed(f64),
+    /// Launches entities at their jump velocity multiplied by the given value
+    ///
+    /// Entities without a `PlatformerControls` component are not launched.
+    JumpMultiplier(f64),
+}
+
+/// Added to an entity with a `JumpPad` component while its pressed image is
+/// shown. The original image of the entity's `Sprite` is restored once the level
+/// clock reaches the given time.
+#[derive(Component, Debug, Clone, PartialEq)]
+#[storage(HashMapStorage)]
+pub struct JumpPadPressed {
+    /// The time (since the level started) at which the original image is
+    /// restored
+    pub until: Duration,
+    /// The image that the `Sprite` had before the jump pad was pressed
+    pub released_image: Image,
+}
+
+/// Moves an entity back and forth or around in a loop along a path
+///
+/// The entity should have a `PhysicsBody` with a kinematic body status so that
+/// it is not affected by gravity or pushed by other entities. Any entities
+/// standing on top of this entity are carried along with it.
+#[derive(Component, Debug, Clone)]
+#[storage(HashMapStorage)]
+pub struct FollowPath {
+    /// The path to move along
+    pub path: Arc<MotionPath>,
+    /// The offset added to each point on the path to get the position of the
+    /// entity
+    ///
+    /// This allows the entity to start anywhere and move in the shape of the
+    /// path, relative to its starting position.
+    pub offset: Vec2,
+    /// The speed at which the entity moves along the path (in world coordinates
+    /// per second)
+    pub speed: f64,
+    pub mode: PathMode,
+    pub easing: Easing,
+}
+
+impl FollowPath {
+    /// Returns the position of the entity at the given time
+    pub fn position_at(&self, elapsed: Duration) -> Vec2 {
+        let &Self {ref path, offset, speed, mode, easing} = self;
+        path.position_at(elapsed, speed, mode, easing) + offset
+    }
+}
+
+/// Added to an entity while it is standing on top of an entity with a
+/// `FollowPath` component, with the velocity of that entity
+///
+/// The physics system adds this velocity to the velocity of the entity's body
+/// during each step, so the entity moves along with whatever is carrying it
+/// while still being stopped by other colliders.
+#[derive(Component, Debug, Clone, PartialEq)]
+#[storage(HashMapStorage)]
+pub struct Carried(pub Vec2);
+
+/// Determines when an entity with a `Toggle` component is turned on and off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ToggleTiming {
+    /// The amount of time that the entity stays on for
+    pub on_duration: Duration,
+    /// The amount of time that the entity stays off for
+    pub off_duration: Duration,
+    /// How far into its cycle the entity is when the level starts
+    ///
+    /// Each cycle starts with the entity turning on.
+    pub phase: Duration,
+}
+
+impl ToggleTiming {
+    /// Returns true if the entity should be on at the given time
+    pub fn is_on_at(&self, elapsed: Duration) -> bool {
+        let &Self {on_duration, off_duration, phase} = self;
+
+        // Computed with floats since adding very long durations together can
+        // overflow a `Duration`
+        let on_secs = on_duration.as_secs_f64();
+        let cycle_secs = on_secs + off_duration.as_secs_f64();
+        // Avoid dividing by zero if the entity never changes
+        if cycle_secs == 0.0 {
+            return true;
+        }
+
+        let time = (elapsed.as_secs_f64() + phase.as_secs_f64()) % cycle_secs;
+        time < on_secs
+    }
+}
+
+/// Turns an entity on and off at regular intervals or when it receives a signal
+///
+/// While an entity is off, it has no collider and cannot touch anything.
+#[derive(Component, Debug, Clone, PartialEq)]
+#[storage(HashMapStorage)]
+pub struct Toggle {
+    /// Determines when the entity turns on and off, or `None` if the entity
+    /// only changes when it receives a signal
+    pub timing: Option<ToggleTiming>,
+    /// false if the entity has been switched off by a signal
+    ///
+    /// An entity that is switched off stays off regardless of its timing.
+    pub switched_on: bool,
+    /// The image that the `Sprite` of this entity switches to while it is off
+    /// (if any)
+    pub off_image: Option<Image>,
+}
+
+impl Default for Toggle {
+    fn default() -> Self {
+        Self {
+            timing: None,
+            switched_on: true,
+            off_image: None,
+        }
+    }
+}
+
+impl Toggle {
+    /// Returns true if the entity should be on at the given time
+    pub fn is_on_at(&self, elapsed: Duration) -> bool {
+        let &Self {timing, switched_on, off_image: _} = self;
+        switched_on && timing.map(|timing| timing.is_on_at(elapsed)).unwrap_or(true)
+    }
+
+    /// Updates whether the entity is switched on based on the given message
+    pub fn receive(&mut self, message: SignalMessage) {
+        self.switched_on = match message {
+            SignalMessage::Toggle => !self.switched_on,
+            SignalMessage::On => true,
+            SignalMessage::Off => false,
+        };
+    }
+}
+
+/// Added to an entity while it is turned off. Everything that was removed from
+/// the entity to turn it off is stored so it can be restored once the entity
+/// turns back on.
+#[derive(Component, Debug, Clone)]
+#[storage(HashMapStorage)]
+pub struct ToggledOff {
+    /// The collider that the entity had before it was turned off (if any)
+    pub collider: Option<PhysicsCollider>,
+    /// The image that the `Sprite` had before the entity was turned off, if
+    /// the image was replaced
+    pub on_image: Option<Image>,
+    /// The animation that was removed from the entity so that it would not
+    /// replace the image shown while the entity is off
+    pub animation: Option<AnimatedSprite>,
+}
+
+/// Sends a signal when an entity with a `PlatformerControls` component presses
+/// its interact key while overlapping this entity
+#[derive(Component, Debug, Clone, PartialEq)]
+#[storage(HashMapStorage)]
+pub struct Switch {
+    /// The name of the signal sent to every entity with a `SignalListener` for
+    /// that signal (if any)
+    pub signal: Option<String>,
+    /// The ID of an object that the signal is also sent to (if any)
+    pub target: Option<u32>,
+    /// The message sent with the signal
+    pub message: SignalMessage,
+    /// The image that the `Sprite` of this entity switches to when it is
+    /// activated (if any)
+    ///
+    /// The image is swapped with the current image of the sprite each time the
+    /// switch is activated so that the switch alternates between both images.
+    pub switched_image: Option<Image>,
+}
+
+/// Receives the messages sent with the signal of the given name
+#[derive(Component, Debug, Clone, PartialEq, Eq, Hash)]
+#[storage(HashMapStorage)]
+pub struct SignalListener {
+    pub signal: String,
+}
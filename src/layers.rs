@@ -1,9 +1,10 @@
-use std::collections::HashMap;
+use std::sync::Arc;
+use std::collections::{HashMap, BTreeMap};
 
 use noisy_float::types::R64;
 use sdl2::rect::Rect;
 
-use crate::{Size, Vec2, TileId, ImageId, Shape};
+use crate::{Size, Vec2, Isometry, TileId, ImageId, Shape, Animation};
 
 /// Defines how an image is to be aligned
 ///
@@ -36,15 +37,44 @@ impl Default for Align {
     }
 }
 
+impl Align {
+    /// Returns the position of the top left corner of a rectangle with the
+    /// given size, relative to the point it is aligned with
+    pub fn top_left(self, size: Vec2) -> Vec2 {
+        let (width, height) = (size.x, size.y);
+        let (x, y) = match self {
+            Align::TopLeft => (0.0, 0.0),
+            Align::Top => (-width / 2.0, 0.0),
+            Align::TopRight => (-width, 0.0),
+            Align::Left => (0.0, -height / 2.0),
+            Align::Center => (-width / 2.0, -height / 2.0),
+            Align::Right => (-width, -height / 2.0),
+            Align::BottomLeft => (0.0, -height),
+            Align::Bottom => (-width / 2.0, -height),
+            Align::BottomRight => (-width, -height),
+        };
+
+        Vec2::new(x, y)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TileImage {
     /// The ID of the image in the renderer image cache (for quick lookups
     /// without needing to store the path here)
     pub id: ImageId,
+    /// The region of the image that contains this tile, or `None` if the
+    /// entire image should be used
+    pub src: Option<Rect>,
     /// The size of the image in pixels
     pub size: Size,
-    /// The alignment of this image within its containing tile
+    /// The alignment of this image when it is used by a tile object
+    ///
+    /// Tiles in tile layers are always aligned with the bottom left corner of
+    /// their grid cell.
     pub align: Align,
+    /// The offset (in pixels) applied when drawing this image
+    pub offset: Vec2,
 }
 
 #[derive(Debug)]
@@ -52,8 +82,12 @@ pub struct Tile {
     pub id: TileId,
     /// The image drawn at this tile's position
     pub image: TileImage,
+    /// The animation used to change the image of this tile over time (if any)
+    pub animation: Option<Arc<Animation>>,
     /// Any coordinates in the geometry are relative to the position of the tile
-    pub collision_geometry: Vec<(Vec2, Shape)>,
+    ///
+    /// Each shape is paired with its position and rotation.
+    pub collision_geometry: Vec<(Isometry, Shape)>,
     /// The type field provided in the tile map (possibly an empty string)
     pub tile_type: String,
     /// Any custom properites on the tile itself
@@ -77,6 +111,9 @@ pub struct Image {
     ///
     /// See the documentation on the `Align` type for more info.
     pub align: Align,
+    /// An offset (in world coordinates) applied to the position of the image
+    /// after it has been aligned
+    pub offset: Vec2,
     /// Additional parameters used when drawing the image
     pub params: ImageParams,
 }
@@ -104,24 +141,78 @@ pub struct ImageParams {
 pub struct TileLayer {
     /// The offset of all tiles in this layer
     pub offset: Vec2,
-    /// The number of rows in the grid of tiles
-    pub nrows: usize,
-    /// The number of columns in the grid of tiles
-    pub ncols: usize,
-    /// The tiles in the layer, stored row-wise
-    pub tiles: Vec<Vec<Option<Image>>>,
+    /// The tiles in the layer, indexed by `(row, col)` so that iterating over
+    /// them yields the tiles in right-down order
+    ///
+    /// Only non-empty tiles are stored, so empty regions of the layer take up
+    /// no memory. Rows and columns may be negative for infinite maps.
+    pub tiles: BTreeMap<(i32, i32), LayerTile>,
+    /// The factor by which the viewport position is multiplied when drawing
+    /// this layer
+    ///
+    /// See `ImageLayer::parallax` for more details.
+    pub parallax: Vec2,
+}
+
+/// A single tile drawn as part of a `TileLayer`
+#[derive(Debug, Clone)]
+pub struct LayerTile {
+    /// The image to draw for this tile
+    pub image: Image,
+    /// If not `None`, the image will be updated to the current frame of this
+    /// animation whenever it is drawn
+    pub animation: Option<Arc<Animation>>,
+}
+
+/// A layer that displays a single image, optionally repeated along each axis
+#[derive(Debug)]
+pub struct ImageLayer {
+    /// The offset of the image in this layer
+    pub offset: Vec2,
+    /// The image to draw
+    pub image: Image,
+    /// true if the image should be repeated along the x-axis to fill the screen
+    pub repeat_x: bool,
+    /// true if the image should be repeated along the y-axis to fill the screen
+    pub repeat_y: bool,
+    /// The factor by which the viewport position is multiplied when drawing
+    /// this layer
+    ///
+    /// A factor of 1.0 moves the layer with the rest of the map. Values below
+    /// 1.0 make the layer scroll more slowly than the viewport, which can be
+    /// used to make backgrounds appear further away. A factor of 0.0 keeps the
+    /// layer fixed on the screen.
+    pub parallax: Vec2,
+}
+
+#[derive(Debug)]
+pub enum ExtraLayer {
+    Tiles(TileLayer),
+    Image(ImageLayer),
 }
 
 #[derive(Debug, Default)]
 pub struct ExtraLayers {
-    /// The layers that should be drawn in front of the map layer, in drawing
+    /// The layers that should be drawn in front of every sprite, in drawing
     /// order (back to front)
     ///
-    /// These layers appear above the map layer in Tiled.
-    pub front_layers: Vec<TileLayer>,
-    /// The layers that should be drawn behind the map layer, in drawing
+    /// These layers have their `above_sprites` property set to true.
+    pub front_layers: Vec<ExtraLayer>,
+    /// The layers that are drawn in between sprites, in drawing order (back to
+    /// front), each paired with the index of the layer in Tiled
+    ///
+    /// Each layer is drawn in front of the sprites with a lower draw layer and
+    /// behind the sprites with a higher draw layer (see `DrawLayer`).
+    pub layers: Vec<(u32, ExtraLayer)>,
+    /// The layers that should be drawn behind every sprite, in drawing
     /// order (back to front)
     ///
-    /// These layers appear below the map layer in Tiled.
-    pub back_layers: Vec<TileLayer>,
+    /// These layers have their `above_sprites` property set to false.
+    pub back_layers: Vec<ExtraLayer>,
+    /// The draw layer used for sprites without a `DrawLayer` component
+    ///
+    /// This is the index of the first tile layer that contains entities. If
+    /// `None`, those sprites are drawn in front of all of the layers in
+    /// `layers`.
+    pub default_draw_layer: Option<u32>,
 }
@@ -2,10 +2,14 @@ mod image_params;
 mod load_tilesets;
 mod load_layers;
 mod load_objects;
+mod load_config;
+mod tile_images;
+mod validate;
 
 use std::fmt;
 use std::io;
-use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::path::PathBuf;
 
 use thiserror::Error;
 use sdl2::{pixels::Color, rect::{Point, Rect}};
@@ -29,12 +33,18 @@ use crate::{
     Player,
     Position,
     Sprite,
+    DrawLayer,
     Vec2,
     ExtraLayers,
+    ComponentTemplate,
+    ComponentTemplates,
+    TemplateTrigger,
     TemplateError,
     Renderer,
     ImageCache,
     TileLayer,
+    ExtraLayer,
+    ImageLayer,
     Image,
     ImageParams,
     SdlError,
@@ -45,11 +55,20 @@ use crate::{
     Modifiers,
     Systems,
     Viewport,
+    Clock,
+    ToggleGroups,
+    ObjectEntities,
+    LayerTile,
 };
 
-use load_tilesets::load_tilesets;
+use load_tilesets::{load_tilesets, tileset_tile_count};
 use load_layers::load_layers;
-use load_objects::load_objects;
+use load_objects::{load_objects, load_paths};
+use load_config::load_config;
+
+pub use load_config::LevelConfig;
+pub use tile_images::TileImages;
+pub use validate::*;
 
 /// The draw order value of tiles inserted into the world from the map layer
 pub(crate) const TILE_DRAW_ORDER: u8 = 0;
@@ -66,6 +85,43 @@ pub enum LoadError {
     #[error("Error with path `{0}`: {1}")]
     IOError(PathBuf, io::Error),
 
+    #[error("expected `{prop}` property of the map to have type `{expected_type}`")]
+    MapPropTypeError {
+        prop: &'static str,
+        expected_type: &'static str,
+    },
+
+    #[error("`{prop}` property of the map has invalid value `{value}`, expected {expected}")]
+    InvalidMapProp {
+        prop: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+
+    #[error("expected `{prop}` property of layer `{layer}` to have type `{expected_type}`")]
+    LayerPropTypeError {
+        layer: String,
+        prop: &'static str,
+        expected_type: &'static str,
+    },
+
+    #[error("layer `{layer}` refers to a tile with ID {tile} that could not be loaded (row {row}, col {col})")]
+    UnknownLayerTile {
+        layer: String,
+        row: i32,
+        col: i32,
+        tile: TileId,
+    },
+
+    #[error("object with ID {object} refers to a tile with ID {tile} that could not be loaded")]
+    UnknownObjectTile {
+        object: u32,
+        tile: TileId,
+    },
+
+    /// A warning that was treated as an error because strict mode was enabled
+    Warning(#[from] LoadWarning),
+
     TemplateError(#[from] TemplateError),
     Unsupported(#[from] Unsupported),
 }
@@ -76,6 +132,130 @@ impl From<(PathBuf, io::Error)> for LoadError {
     }
 }
 
+/// A non-fatal problem found while loading a level
+///
+/// Whatever caused the warning is ignored and loading continues as normal.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum LoadWarning {
+    #[error("the `{prop}` property is not supported on layers that contain entities (ignoring property of layer `{layer}`)")]
+    EntityLayerProp {layer: String, prop: &'static str},
+    #[error("tileset `{tileset}` has more than one tileset image (ignoring tileset)")]
+    MultipleTilesetImages {tileset: String},
+    #[error("tileset `{tileset}` has an image that is too small to contain any tiles (ignoring tileset)")]
+    TilesetImageTooSmall {tileset: String},
+    #[error("tile with ID {tile} does not have exactly 1 image (ignoring tile)")]
+    TileImageCount {tile: TileId},
+    #[error("animation for tile with ID {tile} refers to a tile that could not be loaded (ignoring animation)")]
+    InvalidAnimation {tile: TileId},
+    #[error("image `{}` specifies a transparent color which is not supported yet (ignoring transparent color)", path.display())]
+    TransparentColor {path: PathBuf},
+    #[error("ignoring duplicate `level_start` indicator (object ID = {object})")]
+    DuplicateLevelStart {object: u32},
+    #[error("the `level_start` indicator should be a single point (object ID = {object})")]
+    LevelStartNotPoint {object: u32},
+    #[error("the `path` object should be a polyline or polygon (object ID = {object})")]
+    PathNotPolyline {object: u32},
+}
+
+impl LoadWarning {
+    /// The name of the layer that caused this warning (if any)
+    pub fn layer(&self) -> Option<&str> {
+        use LoadWarning::*;
+        match self {
+            EntityLayerProp {layer, ..} => Some(layer),
+            _ => None,
+        }
+    }
+
+    /// The name of the tileset that caused this warning (if any)
+    pub fn tileset(&self) -> Option<&str> {
+        use LoadWarning::*;
+        match self {
+            MultipleTilesetImages {tileset} |
+            TilesetImageTooSmall {tileset} => Some(tileset),
+            _ => None,
+        }
+    }
+
+    /// The ID of the tile that caused this warning (if any)
+    pub fn tile(&self) -> Option<TileId> {
+        use LoadWarning::*;
+        match *self {
+            TileImageCount {tile} |
+            InvalidAnimation {tile} => Some(tile),
+            _ => None,
+        }
+    }
+
+    /// The ID of the object that caused this warning (if any)
+    pub fn object(&self) -> Option<u32> {
+        use LoadWarning::*;
+        match *self {
+            DuplicateLevelStart {object} |
+            LevelStartNotPoint {object} |
+            PathNotPolyline {object} => Some(object),
+            _ => None,
+        }
+    }
+}
+
+/// Collects the warnings produced while loading a level
+#[derive(Debug)]
+struct LoadWarnings {
+    /// If true, any warning is immediately returned as an error
+    strict: bool,
+    warnings: Vec<LoadWarning>,
+    /// If not `None`, errors that only affect part of the map are collected
+    /// here and loading continues without that part
+    ///
+    /// Used to report every error at once when validating a map.
+    errors: Option<Vec<LoadError>>,
+}
+
+impl LoadWarnings {
+    fn new(strict: bool) -> Self {
+        Self {strict, warnings: Vec::new(), errors: None}
+    }
+
+    /// Collects warnings and errors without stopping at the first error
+    fn collect_errors() -> Self {
+        Self {strict: false, warnings: Vec::new(), errors: Some(Vec::new())}
+    }
+
+    /// Returns the error, or records it if errors are being collected
+    fn error(&mut self, err: impl Into<LoadError>) -> Result<(), LoadError> {
+        match &mut self.errors {
+            Some(errors) => {
+                errors.push(err.into());
+                Ok(())
+            },
+            None => Err(err.into()),
+        }
+    }
+
+    /// Returns the value of the result, or records its error and returns the
+    /// default value if errors are being collected
+    fn recover<T: Default>(&mut self, result: Result<T, LoadError>) -> Result<T, LoadError> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(err) => {
+                self.error(err)?;
+                Ok(T::default())
+            },
+        }
+    }
+
+    /// Records a warning, or returns it as an error in strict mode
+    fn warn(&mut self, warning: LoadWarning) -> Result<(), LoadError> {
+        if self.strict {
+            return Err(warning.into());
+        }
+
+        self.warnings.push(warning);
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Error)]
 #[error("{0}")]
 pub struct Unsupported(pub(crate) String);
@@ -84,6 +264,14 @@ pub struct Unsupported(pub(crate) String);
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct TileId(u32);
 
+impl TileId {
+    /// The global ID of the tile in the map
+    pub fn gid(self) -> u32 {
+        let TileId(id) = self;
+        id
+    }
+}
+
 impl fmt::Display for TileId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let TileId(id) = self;
@@ -105,21 +293,71 @@ macro_rules! unsupported {
     ($($arg:tt)+) => (return Err($crate::Unsupported(format!($($arg)+)).into()));
 }
 
-fn resolve_image_path(base_dir: &Path, image_path: &str) -> Result<PathBuf, LoadError> {
-    let path = Path::new(image_path);
-    let path = if path.is_relative() {
-        base_dir.join(path)
-    } else {
-        path.to_path_buf()
-    };
+fn check_orientation(orientation: tiled::Orientation) -> Result<(), LoadError> {
+    assert_support!(orientation == tiled::Orientation::Orthogonal,
+        "only maps with orthogonal orientation are supported");
+
+    Ok(())
+}
+
+/// Loads the tiles, layers and objects of the map into the given world,
+/// applying the given templates to every entity that is created
+///
+/// Also used by `validate()` so that templates are checked by running them
+/// exactly as they would run when the level is loaded.
+fn load_entities(
+    map: &TileMap,
+    tile_size: Size,
+    templates: &ComponentTemplates,
+    image_cache: &mut ImageCache,
+    world: &mut World,
+    extra_layers: &mut ExtraLayers,
+    level_start: &mut Option<Vec2>,
+    warnings: &mut LoadWarnings,
+) -> Result<(), LoadError> {
+    let tiled::Map {ref layers, ref image_layers, ..} = *map.as_map();
+
+    let tiles = load_tilesets(map, image_cache, warnings)?;
+    // Inserted before any entities are created so templates can use them
+    world.insert(TileImages::new(
+        tiles.iter().map(|(&id, tile)| (id, tile.image.clone())).collect(),
+        map.as_map().tilesets.iter()
+            .map(|tileset| (tileset.first_gid, tileset_tile_count(tileset)))
+            .collect(),
+    ));
+    world.insert(load_paths(map, warnings)?);
+    world.insert(ToggleGroups::default());
+    world.insert(ObjectEntities::default());
+    load_layers(
+        map,
+        layers,
+        image_layers,
+        tile_size,
+        &tiles,
+        templates,
+        image_cache,
+        world,
+        extra_layers,
+        warnings,
+    )?;
+    load_objects(map, &tiles, templates, world, level_start, warnings)?;
 
-    Ok(path.canonicalize().map_err(|err| (path.to_path_buf(), err))?)
+    Ok(())
+}
+
+/// Returns the canonical path of a file (image, music, etc.) referenced by the
+/// map within the map's asset source
+fn resolve_asset_path(map: &TileMap, asset_path: &str) -> Result<PathBuf, LoadError> {
+    // Joining with an absolute path produces the absolute path as-is
+    let path = map.base_dir().join(asset_path);
+    Ok(map.source().canonicalize(&path).map_err(|err| (path, err))?)
 }
 
 #[derive(SystemData)]
 struct RenderData<'a> {
     pub positions: ReadStorage<'a, Position>,
     pub sprites: ReadStorage<'a, Sprite>,
+    pub draw_layers: ReadStorage<'a, DrawLayer>,
 }
 
 pub struct Level {
@@ -132,8 +370,14 @@ pub struct Level {
     tile_size: Size,
     extra_layers: ExtraLayers,
     background_color: Color,
+    /// The templates applied to each entity created from the loaded map
+    templates: ComponentTemplates,
+    /// The configuration from the custom properties of the loaded map
+    config: LevelConfig,
     /// True if load() has completed successfully
     loaded: bool,
+    /// If true, any warnings produced by load() are returned as errors
+    strict: bool,
 }
 
 impl fmt::Debug for Level {
@@ -146,7 +390,10 @@ impl fmt::Debug for Level {
             tile_size,
             extra_layers,
             background_color,
+            templates,
+            config,
             loaded,
+            strict,
         } = self;
 
         f.debug_struct("Level")
@@ -157,7 +404,10 @@ impl fmt::Debug for Level {
             .field("tile_size", &tile_size)
             .field("extra_layers", &extra_layers)
             .field("background_color", &background_color)
+            .field("templates", &templates)
+            .field("config", &config)
             .field("loaded", &loaded)
+            .field("strict", &strict)
             .finish()
     }
 }
@@ -176,6 +426,7 @@ impl Level {
         // Setup resources
         world.insert(EventStream::default());
         world.insert(Viewport(default_viewport));
+        world.insert(Clock::default());
 
         let mut systems = Systems::default();
         systems.setup(&mut world);
@@ -188,7 +439,10 @@ impl Level {
             tile_size: Size {width: 1, height: 1},
             extra_layers: ExtraLayers::default(),
             background_color: Color::BLACK,
+            templates: ComponentTemplates::default(),
+            config: LevelConfig::default(),
             loaded: false,
+            strict: false,
         }
     }
 
@@ -200,51 +454,82 @@ impl Level {
         &mut self.world
     }
 
+    /// Adds a template that will be applied to every entity created from the
+    /// map that matches the given trigger
+    ///
+    /// Templates are applied in the order they are registered, after all of
+    /// the built-in templates. Templates must be registered before `load()` is
+    /// called in order to be applied.
+    pub fn register_template<T>(&mut self, trigger: TemplateTrigger, template: T)
+        where T: ComponentTemplate + 'static,
+    {
+        self.templates.register(trigger, template);
+    }
+
+    /// Sets whether warnings produced while loading a map should be treated as
+    /// errors
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Loads the given map into this level
+    ///
+    /// Returns any non-fatal problems found while loading the map. In strict
+    /// mode, the first problem found is returned as an error instead.
+    ///
+    /// A level may only be loaded once, even if loading fails, since a failed
+    /// load may leave part of the map in the world. Create a new level to try
+    /// again.
+    ///
+    /// The level does not own the physics engine, so the gravity set by the
+    /// map is not applied here. Pass the gravity from `config()` to
+    /// `PhysicsEngine::set_gravity()` after loading the map to apply it.
     pub fn load(
         &mut self,
-        base_dir: &Path,
         map: &TileMap,
         image_cache: &mut ImageCache,
-    ) -> Result<(), LoadError> {
+    ) -> Result<Vec<LoadWarning>, LoadError> {
         let Self {
             world,
             systems: _,
-            viewport: _,
+            viewport,
             level_start,
             extra_layers,
             tile_size,
             background_color,
+            ref templates,
+            config,
             loaded,
+            strict,
         } = self;
 
         if *loaded {
             return Err(LoadError::MultipleLoads);
         }
+        *loaded = true;
 
         let tiled::Map {
             version: _,
             orientation,
-            width: ncols,
-            height: nrows,
+            // Not needed since the layers each contain their own tiles
+            width: _,
+            height: _,
             tile_width,
             tile_height,
-            ref tilesets,
-            ref layers,
-            ref image_layers,
-            ref object_groups,
-            properties: _,
+            // Loaded via the TileMap so additional tileset data is available
+            tilesets: _,
+            // Loaded by `load_entities()`
+            layers: _,
+            image_layers: _,
+            // Loaded via the TileMap so the object group offsets are available
+            object_groups: _,
+            ref properties,
             background_colour: map_background_color,
-            infinite,
+            // Infinite maps are supported by handling layers with chunked data
+            infinite: _,
         } = *map.as_map();
 
-        assert_support!(orientation == tiled::Orientation::Orthogonal,
-            "only maps with orthogonal orientation are supported");
-        assert_support!(!infinite,
-            "only finite maps are supported");
-
-        if !image_layers.is_empty() {
-            println!("Warning: image layers are not supported yet and will be ignored");
-        }
+        check_orientation(orientation)?;
 
         if let Some(tiled::Colour {red, green, blue}) = map_background_color {
             *background_color = Color {r: red, g: green, b: blue, a: 255};
@@ -253,9 +538,28 @@ impl Level {
         tile_size.width = tile_width;
         tile_size.height = tile_height;
 
-        let tiles = load_tilesets(base_dir, tilesets, image_cache)?;
-        load_layers(nrows, ncols, layers, *tile_size, &tiles, world, extra_layers)?;
-        load_objects(object_groups, &tiles, world, level_start)?;
+        *config = load_config(map, properties)?;
+        if let Some(width) = config.viewport_width {
+            viewport.set_width(width);
+        }
+        if let Some(height) = config.viewport_height {
+            viewport.set_height(height);
+        }
+        // The viewport resource is what the systems center on the viewport
+        // target, so it must have the same size
+        world.insert(Viewport(*viewport));
+
+        let mut warnings = LoadWarnings::new(*strict);
+        load_entities(
+            map,
+            *tile_size,
+            templates,
+            image_cache,
+            world,
+            extra_layers,
+            level_start,
+            &mut warnings,
+        )?;
 
         // Update any existing players based on the loaded level start
         if let Some(level_start) = *level_start {
@@ -265,9 +569,27 @@ impl Level {
             }
         }
 
-        *loaded = true;
+        // The level starts once it has finished loading, so the time spent
+        // loading doesn't count towards animations or the time limit
+        world.insert(Clock::default());
 
-        Ok(())
+        Ok(warnings.warnings)
+    }
+
+    /// The configuration from the custom properties of the loaded map
+    pub fn config(&self) -> &LevelConfig {
+        &self.config
+    }
+
+    /// The time left before the time limit of the level runs out, or `None` if
+    /// the level has no time limit
+    ///
+    /// This counts the wall-clock time since the level finished loading, as of
+    /// the last call to `update()`. Time keeps counting even if `update()` is
+    /// not called (e.g. while the game is paused).
+    pub fn time_remaining(&self) -> Option<Duration> {
+        let elapsed = self.world.read_resource::<Clock>().elapsed();
+        self.config.time_limit.map(|limit| limit.saturating_sub(elapsed))
     }
 
     pub fn add_player(&mut self) -> Entity {
@@ -283,6 +605,10 @@ impl Level {
         let Size {width, height} = size;
         self.viewport.set_width(width);
         self.viewport.set_height(height);
+
+        let Viewport(viewport) = &mut *self.world.write_resource::<Viewport>();
+        viewport.set_width(width);
+        viewport.set_height(height);
     }
 
     pub fn update<E>(&mut self, events: &E, physics: &mut PhysicsEngine)
@@ -290,6 +616,8 @@ impl Level {
     {
         // Update events
         self.world.write_resource::<EventStream>().refill(events);
+        // Update the time used for animations
+        self.world.write_resource::<Clock>().tick();
 
         // Allow debug controls to handle events first (and potentially override
         // game behaviour)
@@ -370,7 +698,10 @@ impl Level {
             tile_size,
             ref extra_layers,
             background_color,
+            templates: _,
+            config: _,
             loaded: _,
+            strict: _,
         } = *self;
 
         let Size {width, height} = renderer.size();
@@ -389,12 +720,20 @@ impl Level {
 
         renderer.clear(background_color);
 
-        let ExtraLayers {front_layers, back_layers} = extra_layers;
+        let ExtraLayers {
+            front_layers,
+            layers,
+            back_layers,
+            default_draw_layer,
+        } = extra_layers;
+
+        let elapsed = world.read_resource::<Clock>().elapsed();
 
         for layer in back_layers {
             draw_layer(
                 renderer,
                 layer,
+                elapsed,
                 screen_viewport,
                 tile_size,
                 (scale_x, scale_y),
@@ -404,11 +743,37 @@ impl Level {
         let RenderData {
             positions,
             sprites,
+            draw_layers,
         } = world.system_data();
 
-        for (Position(world_pos), sprite) in (&positions, &sprites).join() {
-            let &world_pos = world_pos;
-            let &Sprite {ref image, align_size, pivot, draw_order} = sprite;
+        // Sort the sprites into draw order so they can be interleaved with the
+        // layers drawn in between them
+        let default_draw_layer = default_draw_layer.unwrap_or(u32::MAX);
+        let mut sorted_sprites: Vec<_> = (&positions, &sprites, draw_layers.maybe()).join()
+            .map(|(&Position(world_pos), sprite, draw_layer)| {
+                let sprite_layer = draw_layer.map(|&DrawLayer(index)| index)
+                    .unwrap_or(default_draw_layer);
+                ((sprite_layer, sprite.draw_order), world_pos, sprite)
+            })
+            .collect();
+        // Stable sort to preserve the order of sprites with the same draw order
+        sorted_sprites.sort_by_key(|&(order, _, _)| order);
+
+        let mut layers = layers.iter().peekable();
+        for ((sprite_layer, _), world_pos, sprite) in sorted_sprites {
+            // Any layers behind this sprite need to be drawn first
+            while let Some((_, layer)) = layers.next_if(|&&(index, _)| index < sprite_layer) {
+                draw_layer(
+                    renderer,
+                    layer,
+                    elapsed,
+                    screen_viewport,
+                    tile_size,
+                    (scale_x, scale_y),
+                )?;
+            }
+
+            let &Sprite {ref image, align_size, pivot, draw_order: _} = sprite;
 
             draw_image(
                 renderer,
@@ -421,10 +786,11 @@ impl Level {
             )?;
         }
 
-        for layer in front_layers {
+        for layer in layers.map(|(_, layer)| layer).chain(front_layers) {
             draw_layer(
                 renderer,
                 layer,
+                elapsed,
                 screen_viewport,
                 tile_size,
                 (scale_x, scale_y),
@@ -439,42 +805,88 @@ impl Level {
 
 fn draw_layer(
     renderer: &mut Renderer,
-    layer: &TileLayer,
+    layer: &ExtraLayer,
+    elapsed: Duration,
     screen_viewport: Rect,
     tile_size: Size,
+    scale: (f64, f64),
+) -> Result<(), SdlError> {
+    match layer {
+        ExtraLayer::Tiles(layer) => {
+            draw_tile_layer(renderer, layer, elapsed, screen_viewport, tile_size, scale)
+        },
+        ExtraLayer::Image(layer) => {
+            draw_image_layer(renderer, layer, screen_viewport, scale)
+        },
+    }
+}
+
+/// Returns the viewport used to draw a layer with the given parallax factor
+fn parallax_viewport(screen_viewport: Rect, parallax: Vec2) -> Rect {
+    // Parallax scrolling is achieved by only moving the layer by a fraction of
+    // the distance moved by the viewport
+    Rect::new(
+        (screen_viewport.x() as f64 * parallax.x) as i32,
+        (screen_viewport.y() as f64 * parallax.y) as i32,
+        screen_viewport.width(),
+        screen_viewport.height(),
+    )
+}
+
+fn draw_image_layer(
+    renderer: &mut Renderer,
+    layer: &ImageLayer,
+    screen_viewport: Rect,
     (scale_x, scale_y): (f64, f64),
 ) -> Result<(), SdlError> {
-    let TileLayer {
+    let &ImageLayer {
         offset,
-        nrows: _,
-        ncols: _,
-        tiles,
+        ref image,
+        repeat_x,
+        repeat_y,
+        parallax,
     } = layer;
 
-    // Draw tiles in right-down order
-    for (row_i, row) in (0u32..).zip(tiles) {
-        for (col_i, image) in (0u32..).zip(row) {
-            let image = match image {
-                Some(image) => image,
-                None => continue,
-            };
-
-            // Compute the position of the tile in world coordinates
+    let layer_viewport = parallax_viewport(screen_viewport, parallax);
+
+    // The area of the world (in world coordinates) visible in the layer viewport
+    let Size {width: screen_width, height: screen_height} = renderer.size();
+    let visible_x = layer_viewport.x() as f64 / scale_x;
+    let visible_y = layer_viewport.y() as f64 / scale_y;
+    let visible_width = screen_width as f64 / scale_x;
+    let visible_height = screen_height as f64 / scale_y;
+
+    // Returns the range of image indexes along an axis needed to cover the
+    // visible area
+    let repeat_range = |repeat: bool, offset: f64, size: u32, visible_start: f64, visible_size: f64| {
+        if !repeat || size == 0 {
+            return 0..1;
+        }
+
+        let size = size as f64;
+        let first = ((visible_start - offset) / size).floor() as i64;
+        let last = ((visible_start + visible_size - offset) / size).ceil() as i64;
+        first..last
+    };
+
+    let Size {width, height} = image.params.size;
+    let cols = repeat_range(repeat_x, offset.x, width, visible_x, visible_width);
+    let rows = repeat_range(repeat_y, offset.y, height, visible_y, visible_height);
+
+    for row_i in rows {
+        for col_i in cols.clone() {
             let world_pos = Vec2::new(
-                (col_i * tile_size.width) as f64 + offset.x,
-                (row_i * tile_size.height) as f64 + offset.y,
+                offset.x + (col_i * width as i64) as f64,
+                offset.y + (row_i * height as i64) as f64,
             );
 
             draw_image(
                 renderer,
                 image,
                 world_pos,
-                tile_size,
-                // Tiles should rotate about the center of their image
-                //TODO: This probably won't work...we need to rotate and then
-                // translate to move back to the right position (so align is enforced)
+                Size {width: 0, height: 0},
                 None,
-                screen_viewport,
+                layer_viewport,
                 (scale_x, scale_y),
             )?;
         }
@@ -483,6 +895,60 @@ fn draw_layer(
     Ok(())
 }
 
+fn draw_tile_layer(
+    renderer: &mut Renderer,
+    layer: &TileLayer,
+    elapsed: Duration,
+    screen_viewport: Rect,
+    tile_size: Size,
+    (scale_x, scale_y): (f64, f64),
+) -> Result<(), SdlError> {
+    let TileLayer {
+        offset,
+        tiles,
+        parallax,
+    } = layer;
+
+    let layer_viewport = parallax_viewport(screen_viewport, *parallax);
+
+    // Draw tiles in right-down order
+    for (&(row_i, col_i), layer_tile) in tiles {
+        let LayerTile {image, animation} = layer_tile;
+
+        let animated_image;
+        let image = match animation {
+            Some(animation) => {
+                let mut image = image.clone();
+                animation.frame_at(elapsed).update_image(&mut image);
+                animated_image = image;
+                &animated_image
+            },
+            None => image,
+        };
+
+        // Compute the position of the tile in world coordinates
+        let world_pos = Vec2::new(
+            col_i as f64 * tile_size.width as f64 + offset.x,
+            row_i as f64 * tile_size.height as f64 + offset.y,
+        );
+
+        draw_image(
+            renderer,
+            image,
+            world_pos,
+            tile_size,
+            // Tiles should rotate about the center of their image
+            //TODO: This probably won't work...we need to rotate and then
+            // translate to move back to the right position (so align is enforced)
+            None,
+            layer_viewport,
+            (scale_x, scale_y),
+        )?;
+    }
+
+    Ok(())
+}
+
 /// The `world_pos` and `world_size` parameters represent the top-left corner
 /// size of the rectangle used to align the image. Use a size of (0, 0) when
 /// drawing something that is only represented by a position (e.g. an entity).
@@ -499,6 +965,7 @@ fn draw_image(
         id,
         src,
         align,
+        offset,
         ref params,
     } = image;
 
@@ -559,6 +1026,11 @@ fn draw_image(
         },
     };
 
+    let image_screen_top_left = image_screen_top_left + Point::new(
+        (offset.x * scale_x) as i32,
+        (offset.y * scale_y) as i32,
+    );
+
     // The area of the screen used by this image
     let image_screen_rect = Rect::new(
         image_screen_top_left.x(),
@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use std::collections::HashMap;
+
+use crate::{Vec2, TileMap};
+
+use super::{LoadError, resolve_asset_path};
+
+/// Level configuration read from the custom properties of a map
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LevelConfig {
+    /// The gravity used by the physics engine while this level is played
+    ///
+    /// Set with the `gravity_x` and `gravity_y` properties. Not applied by
+    /// `Level::load()` since the physics engine is owned by the caller.
+    pub gravity: Option<Vec2>,
+    /// The canonical path of the music that should play in the background of
+    /// this level, within the asset source of the map
+    ///
+    /// Set with the `background_music` property.
+    pub background_music: Option<PathBuf>,
+    /// The width of the viewport in world coordinates (pixels)
+    ///
+    /// Set with the `viewport_width` property, which is measured in tiles.
+    pub viewport_width: Option<u32>,
+    /// The height of the viewport in world coordinates (pixels)
+    ///
+    /// Set with the `viewport_height` property, which is measured in tiles.
+    pub viewport_height: Option<u32>,
+    /// The amount of time that the player has to complete the level
+    ///
+    /// Set with the `time_limit` property, which is measured in seconds.
+    pub time_limit: Option<Duration>,
+}
+
+pub fn load_config(
+    map: &TileMap,
+    props: &HashMap<String, tiled::PropertyValue>,
+) -> Result<LevelConfig, LoadError> {
+    let gravity_x = map_f64_prop(props, "gravity_x")?;
+    let gravity_y = map_f64_prop(props, "gravity_y")?;
+    let gravity = match (gravity_x, gravity_y) {
+        (None, None) => None,
+        (x, y) => Some(Vec2::new(x.unwrap_or(0.0), y.unwrap_or(0.0))),
+    };
+
+    let background_music = match props.get("background_music") {
+        Some(tiled::PropertyValue::FileValue(path)) |
+        Some(tiled::PropertyValue::StringValue(path)) => Some(resolve_asset_path(map, path)?),
+        Some(_) => return Err(LoadError::MapPropTypeError {
+            prop: "background_music",
+            expected_type: "file",
+        }),
+        None => None,
+    };
+
+    // The viewport size is specified in tiles
+    let viewport_width = map_tiles_prop(props, "viewport_width", map.tile_width())?;
+    let viewport_height = map_tiles_prop(props, "viewport_height", map.tile_height())?;
+
+    let time_limit = match map_f64_prop(props, "time_limit")? {
+        // Rejects negative numbers, NaN, infinity and anything too large to be
+        // stored in a `Duration`
+        Some(seconds) => Some(Duration::try_from_secs_f64(seconds).map_err(|_| LoadError::MapPropTypeError {
+            prop: "time_limit",
+            expected_type: "non-negative float",
+        })?),
+        None => None,
+    };
+
+    Ok(LevelConfig {gravity, background_music, viewport_width, viewport_height, time_limit})
+}
+
+fn map_f64_prop(
+    props: &HashMap<String, tiled::PropertyValue>,
+    prop: &'static str,
+) -> Result<Option<f64>, LoadError> {
+    match props.get(prop) {
+        Some(&tiled::PropertyValue::FloatValue(value)) => Ok(Some(value as f64)),
+        Some(&tiled::PropertyValue::IntValue(value)) => Ok(Some(value as f64)),
+        Some(_) => Err(LoadError::MapPropTypeError {prop, expected_type: "float"}),
+        None => Ok(None),
+    }
+}
+
+/// Gets a property measured in tiles and converts it to pixels using the given
+/// tile size
+fn map_tiles_prop(
+    props: &HashMap<String, tiled::PropertyValue>,
+    prop: &'static str,
+    tile_size: u32,
+) -> Result<Option<u32>, LoadError> {
+    let tiles = match map_u32_prop(props, prop)? {
+        Some(tiles) => tiles,
+        None => return Ok(None),
+    };
+
+    tiles.checked_mul(tile_size).map(Some).ok_or_else(|| LoadError::InvalidMapProp {
+        prop,
+        value: tiles.to_string(),
+        expected: "a number of tiles that fits in a 32-bit number of pixels",
+    })
+}
+
+fn map_u32_prop(
+    props: &HashMap<String, tiled::PropertyValue>,
+    prop: &'static str,
+) -> Result<Option<u32>, LoadError> {
+    match props.get(prop) {
+        Some(&tiled::PropertyValue::IntValue(value)) if value >= 0 => Ok(Some(value as u32)),
+        Some(_) => Err(LoadError::MapPropTypeError {prop, expected_type: "non-negative int"}),
+        None => Ok(None),
+    }
+}
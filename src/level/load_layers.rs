@@ -1,11 +1,16 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, BTreeMap};
 
 use specs::{World, WorldExt, Builder};
 use noisy_float::types::R64;
 
 use crate::{
     Size,
+    TileMap,
     ExtraLayers,
+    ExtraLayer,
+    ImageLayer,
+    ImageCache,
+    Align,
     Tile,
     TileImage,
     TileLayer,
@@ -13,27 +18,50 @@ use crate::{
     Image,
     ImageParams,
     Sprite,
-    ApplyComponentTemplates,
+    AnimatedSprite,
+    DrawLayer,
+    LayerTile,
+    ComponentTemplates,
+    TemplateOrigin,
+    MapProps,
     Position,
     Shape,
     PhysicsCollider,
     EntityEditor,
-    unsupported,
 };
 
-use super::{TILE_DRAW_ORDER, TileId, LoadError, image_params::TiledImageParams};
+use super::{
+    TILE_DRAW_ORDER,
+    TileId,
+    LoadError,
+    LoadWarning,
+    LoadWarnings,
+    resolve_asset_path,
+    image_params::TiledImageParams,
+};
+
+/// Properties of tile layers that are ignored for layers that contain entities
+const ENTITY_LAYER_IGNORED_PROPS: &[&str] = &["parallax_x", "parallax_y", "above_sprites"];
 
 pub fn load_layers(
-    nrows: u32,
-    ncols: u32,
+    map: &TileMap,
     layers: &[tiled::Layer],
+    image_layers: &[tiled::ImageLayer],
     tile_size: Size,
     tiles: &HashMap<TileId, Tile>,
+    templates: &ComponentTemplates,
+    image_cache: &mut ImageCache,
     world: &mut World,
     extra_layers: &mut ExtraLayers,
+    warnings: &mut LoadWarnings,
 ) -> Result<(), LoadError> {
+    // Extra layers along with their layer index so they can be sorted into
+    // draw order
+    let mut front_layers = Vec::new();
+    let mut mid_layers = Vec::new();
+    let mut back_layers = Vec::new();
+
     let mut prev_layer_index = 0;
-    let mut found_map = false;
     for layer in layers {
         let tiled::Layer {
             ref name,
@@ -41,7 +69,7 @@ pub fn load_layers(
             // A layer's visibility in Tiled does not affect its visibility here
             visible: _,
             tiles: ref layer_data,
-            properties: _,
+            ref properties,
             layer_index,
             offset_x,
             offset_y,
@@ -53,108 +81,212 @@ pub fn load_layers(
             "bug: this code assumes that the layers are stored in draw order");
         prev_layer_index = layer_index;
 
-        let layer_tiles = match layer_data {
-            tiled::LayerData::Finite(layer_tiles) => layer_tiles,
-            //TODO: Infinite maps should be reasonably easy to support if we make an iterator over
-            // LayerData that yields world_pos and LayerTile
-            tiled::LayerData::Infinite(..) => unsupported!("infinite map layers are not supported yet"),
-        };
-
-        if name.trim().eq_ignore_ascii_case("map") {
-            if found_map {
-                // Not sure if having multiple map layers will cause problems.
-                // Going to disable it for now until someone asks for it.
-                println!("Warning: only a single layer should be named `map` (ignoring layer)");
-                continue;
+        if warnings.recover(is_entity_layer(name, properties))? {
+            // Entities are drawn as sprites, so these properties can't be
+            // applied to them
+            for &prop in ENTITY_LAYER_IGNORED_PROPS {
+                if properties.contains_key(prop) {
+                    warnings.warn(LoadWarning::EntityLayerProp {layer: name.clone(), prop})?;
+                }
             }
 
-            load_map_layer(
-                layer_tiles,
+            // Tiles in the map layer are solid unless configured otherwise
+            let collidable = warnings.recover(layer_bool_prop(name, properties, "collidable"))?
+                .unwrap_or_else(|| is_map_layer(name));
+
+            load_entity_layer(
+                map,
+                name,
+                layer_data,
+                layer_index,
                 layer_offset,
                 tile_size,
                 opacity as f64,
+                collidable,
                 tiles,
+                templates,
                 world,
+                warnings,
             )?;
 
-            found_map = true;
+            // Sprites that aren't part of any layer are drawn with the first
+            // entity layer
+            if extra_layers.default_draw_layer.is_none() {
+                extra_layers.default_draw_layer = Some(layer_index);
+            }
 
         } else {
+            let parallax = Vec2::new(
+                warnings.recover(layer_f64_prop(name, properties, "parallax_x"))?.unwrap_or(1.0),
+                warnings.recover(layer_f64_prop(name, properties, "parallax_y"))?.unwrap_or(1.0),
+            );
+
             let layer = to_extra_layer(
-                layer_tiles,
+                name,
+                layer_data,
                 layer_offset,
                 opacity as f64,
-                nrows as usize,
-                ncols as usize,
+                parallax,
                 tiles,
-            );
+                warnings,
+            )?;
 
-            if found_map {
-                extra_layers.front_layers.push(layer);
-            } else {
-                extra_layers.back_layers.push(layer);
+            match warnings.recover(layer_bool_prop(name, properties, "above_sprites"))? {
+                Some(true) => front_layers.push((layer_index, ExtraLayer::Tiles(layer))),
+                Some(false) => back_layers.push((layer_index, ExtraLayer::Tiles(layer))),
+                None => mid_layers.push((layer_index, ExtraLayer::Tiles(layer))),
             }
         }
     }
 
+    for layer in image_layers {
+        let tiled::ImageLayer {ref name, ref properties, layer_index, ..} = *layer;
+        let above_sprites = warnings.recover(layer_bool_prop(name, properties, "above_sprites"))?;
+
+        let layer = match to_image_layer(map, layer, image_cache, warnings)? {
+            Some(layer) => layer,
+            None => continue,
+        };
+
+        match above_sprites {
+            Some(true) => front_layers.push((layer_index, ExtraLayer::Image(layer))),
+            Some(false) => back_layers.push((layer_index, ExtraLayer::Image(layer))),
+            None => mid_layers.push((layer_index, ExtraLayer::Image(layer))),
+        }
+    }
+
+    // Stable sort to preserve the order of layers with the same index
+    front_layers.sort_by_key(|&(layer_index, _)| layer_index);
+    mid_layers.sort_by_key(|&(layer_index, _)| layer_index);
+    back_layers.sort_by_key(|&(layer_index, _)| layer_index);
+
+    extra_layers.front_layers.extend(front_layers.into_iter().map(|(_, layer)| layer));
+    extra_layers.layers.extend(mid_layers);
+    extra_layers.back_layers.extend(back_layers.into_iter().map(|(_, layer)| layer));
+
     Ok(())
 }
 
-fn load_map_layer(
-    layer_tiles: &[Vec<tiled::LayerTile>],
+pub(super) fn is_map_layer(name: &str) -> bool {
+    name.trim().eq_ignore_ascii_case("map")
+}
+
+/// Returns true if the tiles in the given layer should be inserted into the
+/// world as entities rather than only being drawn
+///
+/// This is true for the `map` layer, any layer with the `collidable` property
+/// set to true, and any layer with the `entities` property set to true.
+pub(super) fn is_entity_layer(
+    name: &str,
+    props: &HashMap<String, tiled::PropertyValue>,
+) -> Result<bool, LoadError> {
+    let is_map = is_map_layer(name);
+    // Collidable tiles must be entities so that they can be collided with
+    let collidable = layer_bool_prop(name, props, "collidable")?.unwrap_or(false);
+    let entities = layer_bool_prop(name, props, "entities")?.unwrap_or(is_map);
+
+    Ok(is_map || collidable || entities)
+}
+
+/// Iterates over every tile in the given layer data, yielding the row and
+/// column of each tile along with the tile itself
+///
+/// For infinite maps, the row and column may be negative.
+fn layer_tiles(
+    layer_data: &tiled::LayerData,
+) -> Box<dyn Iterator<Item=(i32, i32, &tiled::LayerTile)> + '_> {
+    match layer_data {
+        tiled::LayerData::Finite(layer_tiles) => Box::new(
+            (0i32..).zip(layer_tiles).flat_map(|(row_i, row)| {
+                (0i32..).zip(row).map(move |(col_i, tile)| (row_i, col_i, tile))
+            })
+        ),
+
+        tiled::LayerData::Infinite(chunks) => Box::new(
+            chunks.values().flat_map(|chunk| {
+                let &tiled::Chunk {x, y, width: _, height: _, ref tiles} = chunk;
+                (y..).zip(tiles).flat_map(move |(row_i, row)| {
+                    (x..).zip(row).map(move |(col_i, tile)| (row_i, col_i, tile))
+                })
+            })
+        ),
+    }
+}
+
+fn load_entity_layer(
+    map: &TileMap,
+    name: &str,
+    layer_data: &tiled::LayerData,
+    layer_index: u32,
     offset: Vec2,
     tile_size: Size,
     opacity: f64,
+    collidable: bool,
     tiles: &HashMap<TileId, Tile>,
+    templates: &ComponentTemplates,
     world: &mut World,
+    warnings: &mut LoadWarnings,
 ) -> Result<(), LoadError> {
-    for (row_i, row) in (0u32..).zip(layer_tiles) {
-        for (col_i, tile) in (0u32..).zip(row) {
-            let (tile, image) = match process_layer_tile(tiles, tile, opacity) {
-                Some((tile, image)) => (tile, image),
-                None => continue,
-            };
-
-            // Compute the position of the tile in world coordinates
-            let world_pos = Vec2::new(
-                (col_i * tile_size.width) as f64 + offset.x,
-                (row_i * tile_size.height) as f64 + offset.y,
-            );
+    for (row_i, col_i, tile) in layer_tiles(layer_data) {
+        let (tile, image) = match process_layer_tile(tiles, name, row_i, col_i, tile, opacity) {
+            Ok(Some((tile, image))) => (tile, image),
+            Ok(None) => continue,
+            Err(err) => {
+                warnings.error(err)?;
+                continue;
+            },
+        };
 
-            let sprite = Sprite {
-                image,
-                align_size: tile_size,
-                pivot: None,
-                draw_order: TILE_DRAW_ORDER,
-            };
+        // Compute the position of the tile in world coordinates
+        let world_pos = Vec2::new(
+            col_i as f64 * tile_size.width as f64 + offset.x,
+            row_i as f64 * tile_size.height as f64 + offset.y,
+        );
+
+        let sprite = Sprite {
+            image,
+            align_size: tile_size,
+            pivot: None,
+            draw_order: TILE_DRAW_ORDER,
+        };
 
-            let Tile {
-                id,
-                image,
-                collision_geometry,
-                tile_type,
-                props,
-            } = tile;
-
-            // Insert collision geometry as a collider, defaulting to a
-            // rectangle based on the image size if no geometry was provided
-            let shape = Shape::from_shapes(collision_geometry)
-                .unwrap_or_else(|| Shape::rect(image.size));
-
-            let collider = PhysicsCollider {
-                shape,
-                collision_groups: PhysicsCollider::ground_collision_groups(),
-                ..PhysicsCollider::default()
-            };
-
-            let entity = world.create_entity()
-                .with(Position(world_pos))
-                .with(sprite)
-                .with(collider)
-                .build();
-
-            let entity = EntityEditor::new(world, entity);
-            entity.apply_templates(*id, tile_type, props)?
+        let Tile {
+            id,
+            image,
+            animation,
+            collision_geometry,
+            tile_type,
+            props,
+        } = tile;
+
+        // Insert collision geometry as a collider, defaulting to a
+        // rectangle based on the image size if no geometry was provided
+        let shape = Shape::from_shapes(collision_geometry)
+            .unwrap_or_else(|| Shape::rect(image.size));
+
+        // Tiles that aren't collidable still need a collider so they can be
+        // interacted with, but nothing will be stopped by them
+        let collider = PhysicsCollider {
+            shape,
+            collision_groups: PhysicsCollider::ground_collision_groups(),
+            sensor: !collidable,
+            ..PhysicsCollider::default()
+        };
+
+        let mut entity = world.create_entity()
+            .with(Position(world_pos))
+            .with(sprite)
+            .with(DrawLayer(layer_index))
+            .with(collider);
+        if let Some(animation) = animation {
+            entity = entity.with(AnimatedSprite(animation.clone()));
+        }
+        let entity = entity.build();
+
+        let entity = EntityEditor::new(world, entity);
+        let props = MapProps {props, base_dir: map.base_dir()};
+        if let Err(err) = templates.apply(&entity, TemplateOrigin::Tile(*id), tile_type, &props) {
+            warnings.error(err)?;
         }
     }
 
@@ -162,40 +294,155 @@ fn load_map_layer(
 }
 
 fn to_extra_layer(
-    layer_tiles: &[Vec<tiled::LayerTile>],
+    name: &str,
+    layer_data: &tiled::LayerData,
     offset: Vec2,
     opacity: f64,
-    nrows: usize,
-    ncols: usize,
+    parallax: Vec2,
     tiles: &HashMap<TileId, Tile>,
-) -> TileLayer {
-    let mut grid_tiles = Vec::new();
-    for row in layer_tiles {
-        assert!(row.len() <= ncols,
-            "expected `{}` tiles in layer row, found `{}` tiles", ncols, row.len());
-
-        let mut grid_row = Vec::new();
-        for tile in row {
-            let image = process_layer_tile(tiles, tile, opacity)
-                .map(|(_, image)| image);
-            grid_row.push(image);
-        }
+    warnings: &mut LoadWarnings,
+) -> Result<TileLayer, LoadError> {
+    let mut layer_tiles_map = BTreeMap::new();
+    for (row_i, col_i, tile) in layer_tiles(layer_data) {
+        let layer_tile = match process_layer_tile(tiles, name, row_i, col_i, tile, opacity) {
+            Ok(Some((tile, image))) => LayerTile {
+                image,
+                animation: tile.animation.clone(),
+            },
+            Ok(None) => continue,
+            Err(err) => {
+                warnings.error(err)?;
+                continue;
+            },
+        };
 
-        grid_tiles.push(grid_row);
+        layer_tiles_map.insert((row_i, col_i), layer_tile);
     }
 
-    TileLayer {offset, nrows, ncols, tiles: grid_tiles}
+    Ok(TileLayer {offset, tiles: layer_tiles_map, parallax})
+}
+
+/// Loads an image layer, returning `None` if the layer has no image
+fn to_image_layer(
+    map: &TileMap,
+    layer: &tiled::ImageLayer,
+    image_cache: &mut ImageCache,
+    warnings: &mut LoadWarnings,
+) -> Result<Option<ImageLayer>, LoadError> {
+    let tiled::ImageLayer {
+        ref name,
+        opacity,
+        // A layer's visibility in Tiled does not affect its visibility here
+        visible: _,
+        offset_x,
+        offset_y,
+        ref image,
+        ref properties,
+        layer_index: _,
+    } = *layer;
+
+    let &tiled::Image {
+        ref source,
+        width,
+        height,
+        transparent_colour: transparent_color,
+    } = match image {
+        Some(image) => image,
+        None => return Ok(None),
+    };
+
+    let image_path = match resolve_asset_path(map, source) {
+        Ok(image_path) => image_path,
+        Err(err) => {
+            warnings.error(err)?;
+            return Ok(None);
+        },
+    };
+
+    if transparent_color.is_some() {
+        warnings.warn(LoadWarning::TransparentColor {path: image_path.clone()})?;
+    }
+
+    let image_id = image_cache.add(map.source(), image_path);
+
+    let image = Image {
+        id: image_id,
+        src: None,
+        align: Align::TopLeft,
+        // The layer offset is stored in the image layer itself
+        offset: Vec2::default(),
+        params: ImageParams {
+            size: Size {
+                width: width as u32,
+                height: height as u32,
+            },
+            flip_horizontal: false,
+            flip_vertical: false,
+            angle: R64::new(0.0),
+            alpha: (opacity as f64 * u8::MAX as f64).round() as u8,
+        },
+    };
+
+    let parallax = Vec2::new(
+        warnings.recover(layer_f64_prop(name, properties, "parallax_x"))?.unwrap_or(1.0),
+        warnings.recover(layer_f64_prop(name, properties, "parallax_y"))?.unwrap_or(1.0),
+    );
+
+    Ok(Some(ImageLayer {
+        offset: Vec2::new(offset_x as f64, offset_y as f64),
+        image,
+        repeat_x: warnings.recover(layer_bool_prop(name, properties, "repeat_x"))?.unwrap_or(false),
+        repeat_y: warnings.recover(layer_bool_prop(name, properties, "repeat_y"))?.unwrap_or(false),
+        parallax,
+    }))
+}
+
+fn layer_f64_prop(
+    layer: &str,
+    props: &HashMap<String, tiled::PropertyValue>,
+    prop: &'static str,
+) -> Result<Option<f64>, LoadError> {
+    match props.get(prop) {
+        Some(&tiled::PropertyValue::FloatValue(value)) => Ok(Some(value as f64)),
+        Some(&tiled::PropertyValue::IntValue(value)) => Ok(Some(value as f64)),
+        Some(_) => Err(LoadError::LayerPropTypeError {
+            layer: layer.to_string(),
+            prop,
+            expected_type: "float",
+        }),
+        None => Ok(None),
+    }
+}
+
+pub(super) fn layer_bool_prop(
+    layer: &str,
+    props: &HashMap<String, tiled::PropertyValue>,
+    prop: &'static str,
+) -> Result<Option<bool>, LoadError> {
+    match props.get(prop) {
+        Some(&tiled::PropertyValue::BoolValue(value)) => Ok(Some(value)),
+        Some(_) => Err(LoadError::LayerPropTypeError {
+            layer: layer.to_string(),
+            prop,
+            expected_type: "bool",
+        }),
+        None => Ok(None),
+    }
 }
 
 /// Looks up a layer tile in the tiles loaded from the tilesets and computes
 /// the complete image with all parameters that should be drawn for this tile.
 ///
-/// Returns None if the tile is empty
+/// Returns None if the tile is empty. The layer name, row and column are used
+/// to report an error if the tile could not be found.
 fn process_layer_tile<'a>(
     tiles: &'a HashMap<TileId, Tile>,
+    layer: &str,
+    row: i32,
+    col: i32,
     tile: &tiled::LayerTile,
     opacity: f64,
-) -> Option<(&'a Tile, Image)> {
+) -> Result<Option<(&'a Tile, Image)>, LoadError> {
     let &tiled::LayerTile {
         gid,
         flip_h: flip_horizontal,
@@ -212,22 +459,32 @@ fn process_layer_tile<'a>(
     // Tiled global IDs always start at 1, so 0 is used to indicate an
     // empty tile
     if gid == 0 {
-        return None;
+        return Ok(None);
     }
 
     let id = TileId(gid);
-    let tile = &tiles[&id];
+    let tile = tiles.get(&id).ok_or_else(|| LoadError::UnknownLayerTile {
+        layer: layer.to_string(),
+        row,
+        col,
+        tile: id,
+    })?;
 
     let TileImage {
         id: image_id,
+        src,
         size,
-        align,
+        // Tiled always aligns tiles with the bottom left corner of their grid
+        // cell, regardless of the object alignment set in the tileset
+        align: _,
+        offset,
     } = tile.image;
 
     let image = Image {
         id: image_id,
-        src: None,
-        align,
+        src,
+        align: Align::BottomLeft,
+        offset,
         params: ImageParams {
             size,
             flip_horizontal: base_params.flip_horizontal,
@@ -237,5 +494,5 @@ fn process_layer_tile<'a>(
         },
     };
 
-    Some((tile, image))
+    Ok(Some((tile, image)))
 }
@@ -1,3 +1,4 @@
+use std::sync::Arc;
 use std::collections::HashMap;
 
 use specs::{World, WorldExt, Builder};
@@ -6,27 +7,53 @@ use sdl2::rect::Point;
 
 use crate::{
     Size,
+    TileMap,
     Vec2,
     Tile,
     TileImage,
     Image,
     ImageParams,
     Sprite,
+    AnimatedSprite,
+    DrawLayer,
     Position,
-    ApplyComponentTemplates,
+    ComponentTemplates,
+    TemplateOrigin,
+    MapProps,
     JointHashMap,
     EntityEditor,
+    Isometry,
+    Point2,
+    Shape,
+    PhysicsCollider,
+    MotionPath,
+    MotionPaths,
+    ObjectEntities,
+    FLIPPED_HORIZONTALLY_FLAG,
+    FLIPPED_VERTICALLY_FLAG,
+    FLIPPED_DIAGONALLY_FLAG,
+    ALL_FLIP_FLAGS,
 };
 
-use super::{LoadError, TileId, OBJECT_DRAW_ORDER, image_params::TiledImageParams};
+use super::{
+    LoadError,
+    LoadWarning,
+    LoadWarnings,
+    TileId,
+    OBJECT_DRAW_ORDER,
+    image_params::TiledImageParams,
+    load_tilesets::{GeometryTransform, find_tile_data, collision_geometry},
+};
 
 pub fn load_objects(
-    object_groups: &[tiled::ObjectGroup],
+    map: &TileMap,
     tiles: &HashMap<TileId, Tile>,
+    templates: &ComponentTemplates,
     world: &mut World,
     level_start: &mut Option<Vec2>,
+    warnings: &mut LoadWarnings,
 ) -> Result<(), LoadError> {
-    for group in object_groups {
+    for (group_index, group) in map.as_map().object_groups.iter().enumerate() {
         let &tiled::ObjectGroup {
             name: _,
             opacity,
@@ -34,14 +61,12 @@ pub fn load_objects(
             ref objects,
             // Objects aren't drawn, so we can ignore the color
             colour: _,
-            layer_index: _,
-            properties: _,
+            layer_index,
+            properties: ref group_props,
         } = group;
         let opacity = opacity as f64;
 
-        //TODO: Use the actual layer offset once we are using a library that
-        // actually provides that to us
-        let layer_offset = Vec2::default();
+        let layer_offset = map.object_group_offset(group_index);
 
         for object in objects {
             let &tiled::Object {
@@ -49,8 +74,8 @@ pub fn load_objects(
                 gid,
                 name: _,
                 ref obj_type,
-                width: _,
-                height: _,
+                width,
+                height,
                 x,
                 y,
                 rotation,
@@ -75,19 +100,13 @@ pub fn load_objects(
                     properties,
                     world,
                     level_start,
+                    warnings,
                 )?;
 
             } else {
                 // The tiled crate doesn't account for flipping info in the gid
                 // so we have to do it manually
                 // See: https://docs.rs/tiled/0.9.2/src/tiled/lib.rs.html#639-660
-                const FLIPPED_HORIZONTALLY_FLAG: u32 = 0x80000000;
-                const FLIPPED_VERTICALLY_FLAG: u32 = 0x40000000;
-                const FLIPPED_DIAGONALLY_FLAG: u32 = 0x20000000;
-                const ALL_FLIP_FLAGS: u32 = FLIPPED_HORIZONTALLY_FLAG
-                    | FLIPPED_VERTICALLY_FLAG
-                    | FLIPPED_DIAGONALLY_FLAG;
-
                 let flags = gid & ALL_FLIP_FLAGS;
                 let gid = gid & !ALL_FLIP_FLAGS;
                 // Swap x and y axis (anti-diagonally) [flips over y = -x line]
@@ -104,18 +123,37 @@ pub fn load_objects(
                 }.normalize();
 
                 let tile_id = TileId(gid);
-                let tile = &tiles[&tile_id];
+                let tile = match tiles.get(&tile_id) {
+                    Some(tile) => tile,
+                    None => {
+                        warnings.error(LoadError::UnknownObjectTile {object: id, tile: tile_id})?;
+                        continue;
+                    },
+                };
 
                 let TileImage {
                     id: image_id,
-                    size,
+                    src,
+                    size: image_size,
                     align,
+                    offset,
                 } = tile.image;
 
+                // Tile objects can be resized in Tiled, which scales the image
+                let size = if width > 0.0 && height > 0.0 {
+                    Size {
+                        width: width.round() as u32,
+                        height: height.round() as u32,
+                    }
+                } else {
+                    image_size
+                };
+
                 let image = Image {
                     id: image_id,
-                    src: None,
+                    src,
                     align,
+                    offset,
                     params: ImageParams {
                         size,
                         flip_horizontal: base_params.flip_horizontal,
@@ -126,13 +164,18 @@ pub fn load_objects(
                 };
 
                 apply_tile_object_templates(
+                    map,
+                    templates,
+                    id,
                     tile,
                     image,
                     obj_type,
                     world_pos,
-                    shape,
+                    layer_index,
+                    group_props,
                     properties,
                     world,
+                    warnings,
                 )?;
             }
         }
@@ -141,6 +184,50 @@ pub fn load_objects(
     Ok(())
 }
 
+/// Loads every object with the `path` type
+pub fn load_paths(map: &TileMap, warnings: &mut LoadWarnings) -> Result<MotionPaths, LoadError> {
+    let mut paths = HashMap::new();
+
+    for (group_index, group) in map.as_map().object_groups.iter().enumerate() {
+        let layer_offset = map.object_group_offset(group_index);
+
+        for object in &group.objects {
+            let &tiled::Object {id, gid, ref obj_type, x, y, rotation, ref shape, ..} = object;
+            if gid != 0 || obj_type != "path" {
+                continue;
+            }
+
+            let (points, closed) = match shape {
+                tiled::ObjectShape::Polyline {points} => (points, false),
+                tiled::ObjectShape::Polygon {points} => (points, true),
+                _ => {
+                    warnings.warn(LoadWarning::PathNotPolyline {object: id})?;
+                    continue;
+                },
+            };
+            if points.is_empty() {
+                warnings.warn(LoadWarning::PathNotPolyline {object: id})?;
+                continue;
+            }
+
+            // The points are relative to the position of the object and rotate
+            // with the object about that position
+            let world_pos = Vec2::new(
+                x as f64 + layer_offset.x,
+                y as f64 + layer_offset.y,
+            );
+            let transform = Isometry::new(world_pos, (rotation as f64).to_radians());
+            let points = points.iter()
+                .map(|&(px, py)| (transform * Point2::new(px as f64, py as f64)).coords)
+                .collect();
+
+            paths.insert(id, Arc::new(MotionPath::new(points, closed)));
+        }
+    }
+
+    Ok(MotionPaths(paths))
+}
+
 fn apply_object_templates(
     id: u32,
     obj_type: &str,
@@ -149,22 +236,27 @@ fn apply_object_templates(
     props: &HashMap<String, tiled::PropertyValue>,
     world: &mut World,
     level_start: &mut Option<Vec2>,
+    warnings: &mut LoadWarnings,
 ) -> Result<(), LoadError> {
     match obj_type {
         "level_start" => {
             if matches!(shape, tiled::ObjectShape::Point(..)) {
                 if level_start.is_some() {
-                    println!("Warning: ignoring duplicate `level_start` indicator (ID = {})", id);
+                    warnings.warn(LoadWarning::DuplicateLevelStart {object: id})?;
 
                 } else {
                     *level_start = Some(world_pos);
                 }
 
             } else {
-                println!("Warning: The `level_start` indicator should to be a single point (ID = {})", id);
+                warnings.warn(LoadWarning::LevelStartNotPoint {object: id})?;
             }
         },
 
+        // Paths are loaded before any entities are created so that they can
+        // be referenced by templates
+        "path" => {},
+
         //TODO: Process other object types
 
         _ => {},
@@ -174,14 +266,21 @@ fn apply_object_templates(
 }
 
 fn apply_tile_object_templates(
+    map: &TileMap,
+    templates: &ComponentTemplates,
+    obj_id: u32,
     tile: &Tile,
     image: Image,
     obj_type: &str,
     world_pos: Vec2,
-    shape: &tiled::ObjectShape,
+    layer_index: Option<u32>,
+    group_props: &HashMap<String, tiled::PropertyValue>,
     obj_props: &HashMap<String, tiled::PropertyValue>,
     world: &mut World,
+    warnings: &mut LoadWarnings,
 ) -> Result<(), LoadError> {
+    let collider = tile_object_collider(&map.as_map().tilesets, tile, &image)?;
+
     // Tile object positions are already set with the alignment in mind so we
     // can get the correct alignment by assuming that we're aligning with a
     // single point
@@ -196,11 +295,11 @@ fn apply_tile_object_templates(
     };
 
     let Tile {
-        id,
+        id: tile_id,
         image: _,
-        //TODO: Insert collision geometry or a default rectangle geometry
-        // based on the object shape if this is empty
-        collision_geometry,
+        animation,
+        // Recomputed based on the size and flips of the object
+        collision_geometry: _,
         tile_type,
         props: tile_props,
     } = tile;
@@ -211,19 +310,100 @@ fn apply_tile_object_templates(
     } else {
         obj_type
     };
-    // Allow object properties to override tile properties
+    // Properties on the object group act as defaults for every object in the
+    // group, so they can be overridden by tile properties
+    let mut base_props = group_props.clone();
+    base_props.extend(tile_props.iter().map(|(key, value)| (key.clone(), value.clone())));
+    // Allow object properties to override tile and group properties
     let props = JointHashMap {
-        base: tile_props,
+        base: &base_props,
         data: obj_props,
     };
+    let props = MapProps {props: &props, base_dir: map.base_dir()};
 
-    let entity = world.create_entity()
+    let mut entity = world.create_entity()
         .with(Position(world_pos))
         .with(sprite)
-        .build();
+        .with(collider);
+    if let Some(animation) = animation {
+        entity = entity.with(AnimatedSprite(animation.clone()));
+    }
+    // Objects are drawn in the same order as their object layer
+    if let Some(layer_index) = layer_index {
+        entity = entity.with(DrawLayer(layer_index));
+    }
+    let entity = entity.build();
+
+    // Allows other entities to refer to this entity by its object ID
+    world.write_resource::<ObjectEntities>().0.insert(obj_id, entity);
 
     let entity = EntityEditor::new(world, entity);
-    entity.apply_templates(*id, obj_tile_type, &props)?;
+    let origin = TemplateOrigin::Object {id: obj_id, tile: *tile_id};
+    if let Err(err) = templates.apply(&entity, origin, obj_tile_type, &props) {
+        warnings.error(err)?;
+    }
 
     Ok(())
 }
+
+/// Creates a collider for a tile object from the collision geometry of its
+/// tile, defaulting to a rectangle the size of the object if the tile has no
+/// collision geometry
+///
+/// The geometry is scaled and flipped to match the image of the object. Just
+/// like with tiles in the map layer, the collider is positioned at the top left
+/// corner of the image. That position is rotated with the object about the
+/// position of the object.
+fn tile_object_collider(
+    tilesets: &[tiled::Tileset],
+    tile: &Tile,
+    image: &Image,
+) -> Result<PhysicsCollider, LoadError> {
+    let &Image {
+        id: _,
+        src: _,
+        align,
+        offset,
+        params: ImageParams {
+            size,
+            flip_horizontal,
+            flip_vertical,
+            angle,
+            alpha: _,
+        },
+    } = image;
+
+    let rotation = angle.raw().to_radians();
+    let size_vec = Vec2::new(size.width as f64, size.height as f64);
+    let top_left = align.top_left(size_vec) + offset;
+    let collider_offset = Isometry::rotation(rotation) * top_left;
+
+    // A negative scale flips the geometry along that axis
+    let image_size = tile.image.size;
+    let scale_factor = |size: u32, image_size: u32, flip: bool| {
+        let scale = if image_size == 0 { 1.0 } else { size as f64 / image_size as f64 };
+        if flip { -scale } else { scale }
+    };
+    let transform = GeometryTransform {
+        image_size: Vec2::new(image_size.width as f64, image_size.height as f64),
+        scale: Vec2::new(
+            scale_factor(size.width, image_size.width, flip_horizontal),
+            scale_factor(size.height, image_size.height, flip_vertical),
+        ),
+    };
+
+    let geometry = match find_tile_data(tilesets, tile.id).and_then(|tile| tile.objectgroup.as_ref()) {
+        Some(objectgroup) => collision_geometry(objectgroup, transform)?,
+        None => Vec::new(),
+    };
+    let shape = Shape::from_shapes(&geometry)
+        .unwrap_or_else(|| Shape::rect(size));
+
+    Ok(PhysicsCollider {
+        shape,
+        offset: collider_offset,
+        rotation,
+        collision_groups: PhysicsCollider::ground_collision_groups(),
+        ..PhysicsCollider::default()
+    })
+}
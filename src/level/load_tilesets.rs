@@ -1,136 +1,424 @@
-use std::path::Path;
+use std::sync::Arc;
 use std::collections::HashMap;
 
 use tiled::Tileset;
+use sdl2::rect::Rect;
 
 use crate::{
-    assert_support,
     unsupported,
     Size,
     Point2,
     Vec2,
+    Isometry,
     Tile,
     TileImage,
     Shape,
-    Align,
+    TileMap,
+    TilesetExtras,
     ImageCache,
     ShapeRect,
-    ShapeCircle,
     ShapePolyline,
-    ShapeConvexPolygon,
+    Animation,
+    AnimationFrame,
 };
 
-use super::{TileId, LoadError, Unsupported, resolve_image_path};
+use super::{TileId, LoadError, LoadWarning, LoadWarnings, Unsupported, resolve_asset_path};
 
 pub fn load_tilesets(
-    base_dir: &Path,
-    tilesets: &[Tileset],
+    map: &TileMap,
     image_cache: &mut ImageCache,
+    warnings: &mut LoadWarnings,
 ) -> Result<HashMap<TileId, Tile>, LoadError> {
+    let tilesets = &map.as_map().tilesets;
     let mut tiles = HashMap::new();
 
     for tileset in tilesets {
+        let extras = map.tileset_extras(tileset.first_gid)
+            .cloned()
+            .unwrap_or_default();
+
         let Tileset {
             first_gid,
             name,
-            tile_width: _,
-            tile_height: _,
-            // Used when tileset is based on a single tileset image
-            spacing: _,
-            // Used when tileset is based on a single tileset image
-            margin: _,
-            //TODO: Could this be used with `tiles.reserve(...)` as a potential
-            // optimization? Might be able to avoid a few allocations
-            tilecount: _,
+            tile_width,
+            tile_height,
+            spacing,
+            margin,
+            tilecount,
             images,
             tiles: tileset_tiles,
             properties: _,
         } = tileset;
 
-        if !images.is_empty() {
-            println!("Warning: Tileset `{}` is based on a single Tileset image and is not supported yet (ignoring tileset)", name);
+        if images.is_empty() {
+            load_image_collection(
+                *first_gid,
+                tileset_tiles,
+                &extras,
+                map,
+                image_cache,
+                &mut tiles,
+                warnings,
+            )?;
             continue;
         }
 
-        for tile in tileset_tiles {
-            let tiled::Tile {
-                id,
-                images,
-                properties,
-                objectgroup,
-                animation: _,
-                tile_type,
-                probability: _,
-            } = tile;
-
-            if images.len() != 1 {
-                println!("Warning: Tile with ID {} does not have exactly 1 image (ignoring tile)", id);
+        // Tiled only supports a single image per tileset, so this is unlikely
+        // to ever happen
+        let image = match &images[..] {
+            [image] => image,
+            _ => {
+                warnings.warn(LoadWarning::MultipleTilesetImages {tileset: name.clone()})?;
                 continue;
-            }
+            },
+        };
 
-            let id = TileId(*first_gid + *id);
+        let &tiled::Image {
+            ref source,
+            width,
+            height,
+            transparent_colour: transparent_color,
+        } = image;
 
-            let &tiled::Image {
-                ref source,
-                width,
-                height,
-                transparent_colour: transparent_color,
-            } = &images[0];
+        let image_path = match resolve_asset_path(map, source) {
+            Ok(image_path) => image_path,
+            Err(err) => {
+                warnings.error(err)?;
+                continue;
+            },
+        };
 
-            let image_path = resolve_image_path(base_dir, source)?;
+        if transparent_color.is_some() {
+            warnings.warn(LoadWarning::TransparentColor {path: image_path.clone()})?;
+        }
 
-            if transparent_color.is_some() {
-                println!("Warning: image `{}` specifies a transparent color which is not supported yet (ignoring transparent color)", image_path.display());
-            }
+        let image_id = image_cache.add(map.source(), image_path);
 
-            let image_id = image_cache.add(image_path);
+        let (ncols, nrows) = tileset_grid_size(tileset, width, height);
+        if ncols == 0 || nrows == 0 {
+            warnings.warn(LoadWarning::TilesetImageTooSmall {tileset: name.clone()})?;
+            continue;
+        }
+
+        let tilecount = tilecount.unwrap_or(ncols * nrows);
+        tiles.reserve(tilecount as usize);
+
+        // Only tiles with custom properties, collision geometry, etc. are
+        // listed in the tileset
+        let tiles_data: HashMap<_, _> = tileset_tiles.iter()
+            .map(|tile| (tile.id, tile))
+            .collect();
+
+        for local_id in 0..tilecount {
+            let row = local_id / ncols;
+            let col = local_id % ncols;
+
+            let src = Rect::new(
+                (margin + col * (tile_width + spacing)) as i32,
+                (margin + row * (tile_height + spacing)) as i32,
+                *tile_width,
+                *tile_height,
+            );
 
             let image = TileImage {
                 id: image_id,
+                src: Some(src),
                 size: Size {
-                    width: width as u32,
-                    height: height as u32,
+                    width: *tile_width,
+                    height: *tile_height,
                 },
-                //TODO: Get alignment from <tileset> tag
-                align: Align::default(),
+                align: extras.object_alignment.unwrap_or_default(),
+                offset: extras.tile_offset,
             };
 
-            let collision_geometry = match &objectgroup {
-                Some(objectgroup) => {
-                    let tiled::ObjectGroup {
-                        name: _,
-                        opacity: _,
-                        visible: _,
-                        objects,
-                        colour: _,
-                        layer_index: _,
-                        properties: _,
-                    } = objectgroup;
-
-                    objects.iter()
-                        .map(object_to_collision_geometry)
-                        .collect::<Result<Vec<_>, _>>()?
-                },
-                None => Vec::new(),
-            };
-
-            let tile = Tile {
-                id,
-                image,
-                collision_geometry,
-                tile_type: tile_type.clone().unwrap_or_default(),
-                props: properties.clone(),
-            };
+            let id = TileId(*first_gid + local_id);
+            let tile = to_tile(id, image, tiles_data.get(&local_id).copied())?;
 
             assert!(tiles.insert(id, tile).is_none(),
                 "bug: tile ID should be unique");
         }
     }
 
+    load_animations(tilesets, &mut tiles, warnings)?;
+
     Ok(tiles)
 }
 
-fn object_to_collision_geometry(obj: &tiled::Object) -> Result<(Vec2, Shape), Unsupported> {
+/// Returns the number of tiles that fit in each row and column of a tileset
+/// image with the given width and height
+///
+/// Accounts for the margin around the image and the spacing between each tile.
+fn tileset_grid_size(tileset: &Tileset, width: i32, height: i32) -> (u32, u32) {
+    let &Tileset {tile_width, tile_height, spacing, margin, ..} = tileset;
+
+    let ncols = (width as u32).saturating_sub(2 * margin) + spacing;
+    let ncols = ncols / (tile_width + spacing);
+    let nrows = (height as u32).saturating_sub(2 * margin) + spacing;
+    let nrows = nrows / (tile_height + spacing);
+
+    (ncols, nrows)
+}
+
+/// Returns the number of local IDs used by the tiles of the given tileset
+pub(super) fn tileset_tile_count(tileset: &Tileset) -> u32 {
+    match &tileset.images[..] {
+        // The tiles in an image collection may have any ID, since IDs are not
+        // reused when tiles are removed
+        [] => tileset.tiles.iter().map(|tile| tile.id + 1).max().unwrap_or(0),
+
+        [image] => tileset.tilecount.unwrap_or_else(|| {
+            let (ncols, nrows) = tileset_grid_size(tileset, image.width, image.height);
+            ncols * nrows
+        }),
+
+        // No tiles are loaded from tilesets with more than one image
+        _ => 0,
+    }
+}
+
+/// Adds the animations from each tileset to the tiles that were loaded from it
+fn load_animations(
+    tilesets: &[Tileset],
+    tiles: &mut HashMap<TileId, Tile>,
+    warnings: &mut LoadWarnings,
+) -> Result<(), LoadError> {
+    for tileset in tilesets {
+        let first_gid = tileset.first_gid;
+
+        for tile in &tileset.tiles {
+            let frames = match &tile.animation {
+                Some(frames) if !frames.is_empty() => frames,
+                _ => continue,
+            };
+
+            let id = TileId(first_gid + tile.id);
+            if !tiles.contains_key(&id) {
+                // Tile was ignored when the tileset was loaded
+                continue;
+            }
+
+            let frames = frames.iter().map(|&tiled::Frame {tile_id, duration}| {
+                let frame_id = TileId(first_gid + tile_id);
+                tiles.get(&frame_id).map(|frame_tile| AnimationFrame {
+                    image: frame_tile.image.clone(),
+                    duration,
+                })
+            }).collect::<Option<Vec<_>>>();
+
+            let frames = match frames {
+                Some(frames) => frames,
+                None => {
+                    warnings.warn(LoadWarning::InvalidAnimation {tile: id})?;
+                    continue;
+                },
+            };
+
+            let tile = tiles.get_mut(&id).expect("bug: tile should exist");
+            tile.animation = Some(Arc::new(Animation::new(frames)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads a tileset where every tile has its own separate image
+fn load_image_collection(
+    first_gid: u32,
+    tileset_tiles: &[tiled::Tile],
+    extras: &TilesetExtras,
+    map: &TileMap,
+    image_cache: &mut ImageCache,
+    tiles: &mut HashMap<TileId, Tile>,
+    warnings: &mut LoadWarnings,
+) -> Result<(), LoadError> {
+    for tile in tileset_tiles {
+        let tiled::Tile {id, ref images, ..} = *tile;
+
+        if images.len() != 1 {
+            warnings.warn(LoadWarning::TileImageCount {tile: TileId(first_gid + id)})?;
+            continue;
+        }
+
+        let id = TileId(first_gid + id);
+
+        let &tiled::Image {
+            ref source,
+            width,
+            height,
+            transparent_colour: transparent_color,
+        } = &images[0];
+
+        let image_path = match resolve_asset_path(map, source) {
+            Ok(image_path) => image_path,
+            Err(err) => {
+                warnings.error(err)?;
+                continue;
+            },
+        };
+
+        if transparent_color.is_some() {
+            warnings.warn(LoadWarning::TransparentColor {path: image_path.clone()})?;
+        }
+
+        let image_id = image_cache.add(map.source(), image_path);
+
+        let image = TileImage {
+            id: image_id,
+            src: None,
+            size: Size {
+                width: width as u32,
+                height: height as u32,
+            },
+            align: extras.object_alignment.unwrap_or_default(),
+            offset: extras.tile_offset,
+        };
+
+        let tile = to_tile(id, image, Some(tile))?;
+
+        assert!(tiles.insert(id, tile).is_none(),
+            "bug: tile ID should be unique");
+    }
+
+    Ok(())
+}
+
+/// Creates a tile with the given image and any additional data (collision
+/// geometry, custom properties, etc.) provided for that tile in the tileset
+fn to_tile(
+    id: TileId,
+    image: TileImage,
+    tile_data: Option<&tiled::Tile>,
+) -> Result<Tile, LoadError> {
+    let tile_data = match tile_data {
+        Some(tile_data) => tile_data,
+        None => return Ok(Tile {
+            id,
+            image,
+            animation: None,
+            collision_geometry: Vec::new(),
+            tile_type: String::new(),
+            props: HashMap::new(),
+        }),
+    };
+
+    let tiled::Tile {
+        id: _,
+        // Images are handled by the caller based on the type of tileset
+        images: _,
+        properties,
+        objectgroup,
+        // Animations can only be loaded once every tile in the tileset has
+        // been loaded (see `load_animations`)
+        animation: _,
+        tile_type,
+        probability: _,
+    } = tile_data;
+
+    let collision_geometry = match &objectgroup {
+        Some(objectgroup) => {
+            let transform = GeometryTransform::identity(image.size);
+            collision_geometry(objectgroup, transform)?
+        },
+        None => Vec::new(),
+    };
+
+    Ok(Tile {
+        id,
+        image,
+        animation: None,
+        collision_geometry,
+        tile_type: tile_type.clone().unwrap_or_default(),
+        props: properties.clone(),
+    })
+}
+
+/// Returns the tile data for the given tile from the tileset that contains it
+pub(super) fn find_tile_data(tilesets: &[Tileset], id: TileId) -> Option<&tiled::Tile> {
+    let TileId(gid) = id;
+
+    // The tileset containing a tile is the one with the largest first GID that
+    // is still less than or equal to the tile's GID
+    let tileset = tilesets.iter()
+        .filter(|tileset| tileset.first_gid <= gid)
+        .max_by_key(|tileset| tileset.first_gid)?;
+
+    let local_id = gid - tileset.first_gid;
+    tileset.tiles.iter().find(|tile| tile.id == local_id)
+}
+
+/// Defines how collision geometry should be scaled and flipped when it is
+/// being loaded
+#[derive(Debug, Clone, Copy)]
+pub(super) struct GeometryTransform {
+    /// The size of the image that the collision geometry was defined for
+    pub image_size: Vec2,
+    /// The scale factor applied along each axis
+    ///
+    /// A negative scale factor flips the geometry along that axis, about the
+    /// center of the image.
+    pub scale: Vec2,
+}
+
+impl GeometryTransform {
+    pub fn identity(image_size: Size) -> Self {
+        Self {
+            image_size: Vec2::new(image_size.width as f64, image_size.height as f64),
+            scale: Vec2::new(1.0, 1.0),
+        }
+    }
+
+    /// Transforms a position relative to the top left corner of the image
+    fn position(&self, x: f64, y: f64) -> Vec2 {
+        let x = if self.scale.x < 0.0 { self.image_size.x - x } else { x };
+        let y = if self.scale.y < 0.0 { self.image_size.y - y } else { y };
+        Vec2::new(x * self.scale.x.abs(), y * self.scale.y.abs())
+    }
+
+    /// Transforms a point relative to the position of a shape
+    fn point(&self, x: f64, y: f64) -> Point2 {
+        Point2::new(x * self.scale.x, y * self.scale.y)
+    }
+
+    /// Transforms the rotation of a shape (in degrees)
+    fn rotation(&self, rotation: f64) -> f64 {
+        // Flipping along a single axis reverses the direction of rotation
+        if (self.scale.x < 0.0) != (self.scale.y < 0.0) {
+            -rotation
+        } else {
+            rotation
+        }
+    }
+}
+
+/// Loads the collision geometry in the given object group, transformed using
+/// the given transform
+pub(super) fn collision_geometry(
+    objectgroup: &tiled::ObjectGroup,
+    transform: GeometryTransform,
+) -> Result<Vec<(Isometry, Shape)>, Unsupported> {
+    let tiled::ObjectGroup {
+        name: _,
+        opacity: _,
+        visible: _,
+        objects,
+        colour: _,
+        layer_index: _,
+        properties: _,
+    } = objectgroup;
+
+    objects.iter()
+        .map(|obj| object_to_collision_geometry(obj, transform))
+        .collect()
+}
+
+/// The number of segments used to approximate an ellipse that is not a circle,
+/// unless otherwise specified using the `ellipse_segments` property
+const DEFAULT_ELLIPSE_SEGMENTS: u32 = 16;
+
+fn object_to_collision_geometry(
+    obj: &tiled::Object,
+    transform: GeometryTransform,
+) -> Result<(Isometry, Shape), Unsupported> {
     let tiled::Object {
         id,
         gid: _,
@@ -140,46 +428,59 @@ fn object_to_collision_geometry(obj: &tiled::Object) -> Result<(Vec2, Shape), Un
         height: _,
         x,
         y,
-        rotation: _,
+        rotation,
         visible: _,
         ref shape,
-        properties: _,
+        ref properties,
     } = *obj;
 
-    let position = Vec2::new(x as f64, y as f64);
+    let position = transform.position(x as f64, y as f64);
+    let scale_x = transform.scale.x.abs();
+    let scale_y = transform.scale.y.abs();
     let shape = match shape {
         tiled::ObjectShape::Point(..) => {
             unsupported!("invalid collision geometry: single point shapes are not supported (object ID = {}", id);
         },
 
         &tiled::ObjectShape::Rect {width, height} => {
-            let half_extents = Vec2::new(width as f64/2.0, height as f64/2.0);
+            let half_extents = Vec2::new(
+                width as f64 * scale_x / 2.0,
+                height as f64 * scale_y / 2.0,
+            );
             Shape::Rect(ShapeRect::new(half_extents))
         },
 
         &tiled::ObjectShape::Ellipse {width, height} => {
-            assert_support!((width - height).abs() < 0.001,
-                "invalid collision geometry: only circles that have an equal width and height are supported (object ID = {})", id);
-            let radius = width as f64 / 2.0;
-            Shape::Circle(ShapeCircle::new(radius))
+            let segments = match properties.get("ellipse_segments") {
+                Some(&tiled::PropertyValue::IntValue(segments)) if segments >= 3 => segments as u32,
+                Some(_) => unsupported!("invalid collision geometry: `ellipse_segments` must be an int that is at least 3 (object ID = {})", id),
+                None => DEFAULT_ELLIPSE_SEGMENTS,
+            };
+
+            Shape::ellipse(width as f64 * scale_x, height as f64 * scale_y, segments)
+                .ok_or_else(|| Unsupported(format!("invalid collision geometry: ellipse must have a non-zero width and height (object ID = {})", id)))?
         },
 
         tiled::ObjectShape::Polyline {points} => {
             let points = points.iter()
-                .map(|&(x, y)| Point2::new(x as f64, y as f64))
+                .map(|&(x, y)| transform.point(x as f64, y as f64))
                 .collect();
             Shape::Polyline(ShapePolyline::new(points, None))
         },
 
         tiled::ObjectShape::Polygon {points} => {
             let points: Vec<_> = points.iter()
-                .map(|&(x, y)| Point2::new(x as f64, y as f64))
+                .map(|&(x, y)| transform.point(x as f64, y as f64))
                 .collect();
-            let polygon = ShapeConvexPolygon::try_from_points(&points)
-                .ok_or_else(|| Unsupported(format!("invalid collision geometry: only convex polygons are supported (object ID = {})", id)))?;
-            Shape::ConvexPolygon(polygon)
+            Shape::polygon(&points)
+                .ok_or_else(|| Unsupported(format!("invalid collision geometry: polygons must not be degenerate or self-intersecting (object ID = {})", id)))?
         },
     };
 
-    Ok((position, shape))
+    // Tiled rotations are in degrees clockwise, which matches the direction of
+    // positive angles since the y-axis points down
+    let rotation = transform.rotation(rotation as f64);
+    let transform = Isometry::new(position, rotation.to_radians());
+
+    Ok((transform, shape))
 }
@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+
+use crate::TileImage;
+
+use super::TileId;
+
+/// The image of every tile loaded from the map
+///
+/// Added to the world as a resource when a map is loaded so that entities can
+/// switch to the image of another tile while the level is played.
+#[derive(Debug, Default)]
+pub struct TileImages {
+    images: HashMap<TileId, TileImage>,
+    /// The first global ID and the number of local IDs of each tileset, in
+    /// ascending order of first global ID
+    tilesets: Vec<(u32, u32)>,
+}
+
+impl TileImages {
+    pub(crate) fn new(images: HashMap<TileId, TileImage>, mut tilesets: Vec<(u32, u32)>) -> Self {
+        tilesets.sort_unstable();
+        Self {images, tilesets}
+    }
+
+    /// Returns the image of the given tile or `None` if that tile was not
+    /// loaded
+    pub fn get(&self, id: TileId) -> Option<&TileImage> {
+        self.images.get(&id)
+    }
+
+    /// Returns the ID of the tile with the given local ID (the ID shown in
+    /// Tiled) in the same tileset as the given tile
+    ///
+    /// Returns `None` if the local ID is outside of that tileset.
+    pub fn tileset_tile(&self, tile: TileId, local_id: u32) -> Option<TileId> {
+        let &(first_gid, tile_count) = self.tilesets.iter().rev()
+            .find(|&&(first_gid, _)| first_gid <= tile.gid())?;
+
+        if local_id >= tile_count {
+            return None;
+        }
+
+        Some(TileId(first_gid + local_id))
+    }
+}
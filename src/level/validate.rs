@@ -0,0 +1,223 @@
+use std::fmt;
+
+use specs::{World, WorldExt};
+
+use crate::{TileMap, Size, ImageCache, ExtraLayers, ComponentTemplates};
+
+use super::{
+    TileId,
+    LoadError,
+    LoadWarning,
+    LoadWarnings,
+    check_orientation,
+    load_entities,
+    load_config::load_config,
+    load_layers::{is_map_layer, is_entity_layer, layer_bool_prop},
+};
+
+/// How serious a problem found during validation is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The map can be loaded, but part of it will be ignored or may not
+    /// behave as expected
+    Warning,
+    /// The map will fail to load
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// The part of the map that a diagnostic refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticLocation {
+    /// The map as a whole
+    Map,
+    Tileset {name: String},
+    Tile {id: TileId},
+    Layer {name: String},
+    /// A single tile within a tile layer
+    LayerTile {layer: String, row: i32, col: i32},
+    Object {id: u32},
+}
+
+impl fmt::Display for DiagnosticLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use DiagnosticLocation::*;
+        match self {
+            Map => write!(f, "map"),
+            Tileset {name} => write!(f, "tileset `{}`", name),
+            Tile {id} => write!(f, "tile {}", id),
+            Layer {name} => write!(f, "layer `{}`", name),
+            LayerTile {layer, row, col} => write!(f, "layer `{}` (row {}, col {})", layer, row, col),
+            Object {id} => write!(f, "object {}", id),
+        }
+    }
+}
+
+/// A problem found while validating a map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub location: DiagnosticLocation,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {severity, location, message} = self;
+        write!(f, "{}: {}: {}", severity, location, message)
+    }
+}
+
+/// Collects diagnostics as the map is validated
+#[derive(Debug, Default)]
+struct Diagnostics(Vec<Diagnostic>);
+
+impl Diagnostics {
+    fn warning(&mut self, location: DiagnosticLocation, message: impl Into<String>) {
+        self.push(Severity::Warning, location, message.into());
+    }
+
+    fn error(&mut self, location: DiagnosticLocation, message: impl Into<String>) {
+        self.push(Severity::Error, location, message.into());
+    }
+
+    fn load_error(&mut self, err: LoadError) {
+        let location = error_location(&err);
+        self.error(location, err.to_string());
+    }
+
+    fn load_warning(&mut self, warning: LoadWarning) {
+        let location = warning_location(&warning);
+        self.warning(location, warning.to_string());
+    }
+
+    fn push(&mut self, severity: Severity, location: DiagnosticLocation, message: String) {
+        self.0.push(Diagnostic {severity, location, message});
+    }
+}
+
+/// Checks the given map for any problems that would occur when it is loaded
+///
+/// The map is loaded into a scratch world the same way that `Level::load()`
+/// loads it, except that loading continues past any error that only affects
+/// part of the map. No images are loaded, so this does not require a window.
+/// The map is only checked for problems that can be found without running the
+/// game. The custom properties used by the built-in templates are checked, but
+/// templates registered with `Level::register_template()` are not.
+pub fn validate(map: &TileMap) -> Vec<Diagnostic> {
+    let mut diagnostics = Diagnostics::default();
+
+    if let Err(err) = check_orientation(map.as_map().orientation) {
+        diagnostics.load_error(err);
+    }
+
+    if let Err(err) = load_config(map, &map.as_map().properties) {
+        diagnostics.load_error(err);
+    }
+
+    let mut warnings = LoadWarnings::collect_errors();
+    let result = load_scratch_world(map, &mut warnings);
+
+    let LoadWarnings {strict: _, warnings, errors} = warnings;
+    for err in errors.into_iter().flatten() {
+        diagnostics.load_error(err);
+    }
+    // Any error that stopped the map from loading was found last
+    if let Err(err) = result {
+        diagnostics.load_error(err);
+    }
+    for warning in warnings {
+        diagnostics.load_warning(warning);
+    }
+
+    validate_collidable(map, &mut diagnostics);
+
+    diagnostics.0
+}
+
+/// Loads the map into a scratch world with the built-in templates
+///
+/// Images are only added to the image cache and never loaded.
+fn load_scratch_world(map: &TileMap, warnings: &mut LoadWarnings) -> Result<(), LoadError> {
+    let mut world = World::new();
+    crate::register_components(&mut world);
+
+    let tiled::Map {tile_width, tile_height, ..} = *map.as_map();
+    let tile_size = Size {width: tile_width, height: tile_height};
+
+    load_entities(
+        map,
+        tile_size,
+        &ComponentTemplates::default(),
+        &mut ImageCache::default(),
+        &mut world,
+        &mut ExtraLayers::default(),
+        &mut None,
+        warnings,
+    )
+}
+
+/// Warns if nothing in the level will be solid, which loads fine but is almost
+/// always a mistake
+fn validate_collidable(map: &TileMap, diagnostics: &mut Diagnostics) {
+    let found_collidable = map.as_map().layers.iter().any(|layer| {
+        let tiled::Layer {ref name, ref properties, ..} = *layer;
+        // Any type errors were already reported by the loader
+        let is_entity_layer = is_entity_layer(name, properties).unwrap_or(false);
+        let collidable = layer_bool_prop(name, properties, "collidable").ok().flatten()
+            .unwrap_or_else(|| is_map_layer(name));
+        is_entity_layer && collidable
+    });
+
+    if !found_collidable {
+        diagnostics.warning(DiagnosticLocation::Map, "no layer is named `map` or has `collidable` set to true (nothing in the level will be solid)");
+    }
+}
+
+fn error_location(err: &LoadError) -> DiagnosticLocation {
+    use LoadError::*;
+    match err {
+        LayerPropTypeError {layer, ..} => DiagnosticLocation::Layer {name: layer.clone()},
+        UnknownLayerTile {layer, row, col, ..} => DiagnosticLocation::LayerTile {
+            layer: layer.clone(),
+            row: *row,
+            col: *col,
+        },
+        &UnknownObjectTile {object, ..} => DiagnosticLocation::Object {id: object},
+        TemplateError(err) => match err.origin() {
+            Some(origin) => match origin.object() {
+                Some(id) => DiagnosticLocation::Object {id},
+                None => DiagnosticLocation::Tile {id: origin.tile()},
+            },
+            None => DiagnosticLocation::Map,
+        },
+        Warning(warning) => warning_location(warning),
+        MultipleLoads |
+        IOError(..) |
+        MapPropTypeError {..} |
+        InvalidMapProp {..} |
+        Unsupported(_) => DiagnosticLocation::Map,
+    }
+}
+
+fn warning_location(warning: &LoadWarning) -> DiagnosticLocation {
+    if let Some(layer) = warning.layer() {
+        DiagnosticLocation::Layer {name: layer.to_string()}
+    } else if let Some(tileset) = warning.tileset() {
+        DiagnosticLocation::Tileset {name: tileset.to_string()}
+    } else if let Some(id) = warning.tile() {
+        DiagnosticLocation::Tile {id}
+    } else if let Some(id) = warning.object() {
+        DiagnosticLocation::Object {id}
+    } else {
+        DiagnosticLocation::Map
+    }
+}
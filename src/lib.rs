@@ -1,6 +1,7 @@
 #![deny(unused_must_use)]
 
 mod math;
+mod assets;
 mod event;
 mod event_stream;
 mod window;
@@ -9,6 +10,8 @@ mod game;
 mod physics;
 mod tile_map;
 mod layers;
+mod animation;
+mod motion_path;
 mod level;
 mod components;
 mod component_templates;
@@ -17,6 +20,7 @@ mod resources;
 mod systems;
 
 pub use math::*;
+pub use assets::*;
 pub use event::*;
 pub use event_stream::*;
 pub use window::*;
@@ -25,6 +29,8 @@ pub use game::*;
 pub use physics::*;
 pub use tile_map::*;
 pub use layers::*;
+pub use animation::*;
+pub use motion_path::*;
 pub use level::*;
 pub use components::*;
 pub use component_templates::*;
@@ -107,15 +107,66 @@ impl Shape {
         Shape::Rect(ShapeRect::new(half_extents))
     }
 
-    pub(crate) fn from_shapes(shapes: &[(Vec2, Self)]) -> Option<Self> {
+    /// Creates a shape from the vertices of a simple polygon
+    ///
+    /// Unlike `ShapeConvexPolygon`, the polygon may be concave. Concave
+    /// polygons are decomposed into a compound shape made up of convex parts.
+    ///
+    /// Returns `None` if the polygon is degenerate or self-intersecting.
+    pub(crate) fn polygon(points: &[Point2]) -> Option<Self> {
+        // Triangulation doesn't detect every self-intersecting polygon
+        if has_crossing_edges(points) {
+            return None;
+        }
+
+        if is_convex(points) {
+            return ShapeConvexPolygon::try_from_points(points).map(Shape::ConvexPolygon);
+        }
+
+        let parts = convex_decomposition(points)?;
+        let parts = parts.into_iter()
+            .map(|part| {
+                let part_points: Vec<_> = part.iter().map(|&i| points[i]).collect();
+                let part = ShapeConvexPolygon::try_from_points(&part_points)?;
+                Some((Isometry::identity(), ShapeHandle::new(part)))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Shape::Compound(ShapeCompound::new(parts)))
+    }
+
+    /// Creates an ellipse with the given width and height, centered at the
+    /// origin
+    ///
+    /// If the width and height are not equal, the ellipse is approximated by a
+    /// convex polygon with the given number of segments.
+    pub(crate) fn ellipse(width: f64, height: f64, segments: u32) -> Option<Self> {
+        if (width - height).abs() < 0.001 {
+            return Some(Shape::Circle(ShapeCircle::new(width / 2.0)));
+        }
+
+        let points: Vec<_> = (0..segments).map(|i| {
+            let angle = i as f64 / segments as f64 * 2.0 * std::f64::consts::PI;
+            Point2::new(width / 2.0 * angle.cos(), height / 2.0 * angle.sin())
+        }).collect();
+
+        ShapeConvexPolygon::try_from_points(&points).map(Shape::ConvexPolygon)
+    }
+
+    pub(crate) fn from_shapes(shapes: &[(Isometry, Self)]) -> Option<Self> {
         match shapes {
             [] => None,
-            [(_, shape)] => Some(shape.clone()),
+            // The position of a single shape is ignored, but its rotation
+            // still needs to be preserved
+            [(transform, shape)] if transform.rotation.angle() == 0.0 => Some(shape.clone()),
+            [(transform, shape)] => {
+                let transform = Isometry::rotation(transform.rotation.angle());
+                let shapes = vec![(transform, shape.to_handle())];
+                Some(Shape::Compound(ShapeCompound::new(shapes)))
+            },
             shapes => {
                 let shapes = shapes.iter()
-                    .map(|(pos, shape)| {
-                        (Isometry::new(*pos, 0.0), shape.to_handle())
-                    })
+                    .map(|(transform, shape)| (*transform, shape.to_handle()))
                     .collect();
                 Some(Shape::Compound(ShapeCompound::new(shapes)))
             },
@@ -123,6 +174,202 @@ impl Shape {
     }
 }
 
+/// Used to account for floating point error in polygon computations
+const POLYGON_EPSILON: f64 = 1e-9;
+
+/// Returns twice the signed area of the given polygon
+///
+/// The result is positive if the points are in counter-clockwise order in a
+/// coordinate system where the y-axis points up (clockwise when the y-axis
+/// points down).
+fn signed_area2(points: &[Point2]) -> f64 {
+    (0..points.len()).map(|i| {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        a.x * b.y - b.x * a.y
+    }).sum()
+}
+
+/// Returns the z component of the cross product of the edges (a, b) and (b, c)
+///
+/// The result is positive if the turn at b has the same orientation as a
+/// polygon with a positive signed area.
+fn cross(a: Point2, b: Point2, c: Point2) -> f64 {
+    let ab = b - a;
+    let bc = c - b;
+    ab.x * bc.y - ab.y * bc.x
+}
+
+/// Returns true if the polygon is convex and does not intersect itself
+fn is_convex(points: &[Point2]) -> bool {
+    let n = points.len();
+    let mut orientation = 0.0;
+    for i in 0..n {
+        let turn = cross(points[i], points[(i + 1) % n], points[(i + 2) % n]);
+        if turn.abs() < POLYGON_EPSILON {
+            continue;
+        }
+
+        if orientation == 0.0 {
+            orientation = turn.signum();
+        } else if turn.signum() != orientation {
+            return false;
+        }
+    }
+
+    // A polygon that always turns the same way can still wind around more than
+    // once (e.g. a pentagram), in which case some of its edges cross
+    !has_crossing_edges(points)
+}
+
+/// Returns true if any two edges of the polygon cross each other
+fn has_crossing_edges(points: &[Point2]) -> bool {
+    let n = points.len();
+    let edge = |i: usize| (points[i], points[(i + 1) % n]);
+
+    (0..n).any(|i| (i+2..n).any(|j| {
+        // The first and last edges are adjacent
+        if i == 0 && j == n - 1 {
+            return false;
+        }
+
+        let (a, b) = edge(i);
+        let (c, d) = edge(j);
+        segments_cross(a, b, c, d)
+    }))
+}
+
+/// Returns true if the segments (a, b) and (c, d) cross at a single point that
+/// is not an endpoint of either segment
+fn segments_cross(a: Point2, b: Point2, c: Point2, d: Point2) -> bool {
+    let opposite = |x: f64, y: f64| {
+        (x > POLYGON_EPSILON && y < -POLYGON_EPSILON)
+            || (x < -POLYGON_EPSILON && y > POLYGON_EPSILON)
+    };
+
+    opposite(cross(a, b, c), cross(a, b, d)) && opposite(cross(c, d, a), cross(c, d, b))
+}
+
+/// Returns true if p is inside or on the boundary of the triangle (a, b, c),
+/// assuming that the triangle has a positive orientation
+fn in_triangle(p: Point2, a: Point2, b: Point2, c: Point2) -> bool {
+    cross(a, b, p) >= -POLYGON_EPSILON
+        && cross(b, c, p) >= -POLYGON_EPSILON
+        && cross(c, a, p) >= -POLYGON_EPSILON
+}
+
+/// Triangulates a simple polygon using ear clipping
+///
+/// Returns triangles made of indexes into `points`, all with a positive
+/// orientation. Returns `None` if the polygon could not be triangulated (e.g.
+/// because it is self-intersecting).
+fn triangulate(points: &[Point2]) -> Option<Vec<[usize; 3]>> {
+    let mut indexes: Vec<usize> = (0..points.len()).collect();
+    if signed_area2(points) < 0.0 {
+        indexes.reverse();
+    }
+
+    let mut triangles = Vec::new();
+    while indexes.len() > 3 {
+        let n = indexes.len();
+        let vertex = |i: usize| {
+            (indexes[(i + n - 1) % n], indexes[i], indexes[(i + 1) % n])
+        };
+
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = vertex(i);
+            cross(points[a], points[b], points[c]) > POLYGON_EPSILON
+                && !indexes.iter().any(|&p| {
+                    p != a && p != b && p != c
+                        && in_triangle(points[p], points[a], points[b], points[c])
+                })
+        });
+
+        match ear {
+            Some(i) => {
+                let (a, b, c) = vertex(i);
+                triangles.push([a, b, c]);
+                indexes.remove(i);
+            },
+
+            None => {
+                // Collinear vertices never form an ear, but can be removed
+                // without changing the shape of the polygon
+                let collinear = (0..n).find(|&i| {
+                    let (a, b, c) = vertex(i);
+                    cross(points[a], points[b], points[c]).abs() <= POLYGON_EPSILON
+                })?;
+                indexes.remove(collinear);
+            },
+        }
+    }
+
+    let [a, b, c] = [indexes[0], indexes[1], indexes[2]];
+    if cross(points[a], points[b], points[c]).abs() > POLYGON_EPSILON {
+        triangles.push([a, b, c]);
+    }
+
+    Some(triangles)
+}
+
+/// Decomposes a simple polygon into convex parts by triangulating it and then
+/// merging adjacent parts as long as the result is still convex
+///
+/// Each part is a list of indexes into `points`.
+fn convex_decomposition(points: &[Point2]) -> Option<Vec<Vec<usize>>> {
+    let mut parts: Vec<Vec<usize>> = triangulate(points)?.iter()
+        .map(|triangle| triangle.to_vec())
+        .collect();
+
+    'merge: loop {
+        for i in 0..parts.len() {
+            for j in i+1..parts.len() {
+                if let Some(merged) = merge_convex_parts(points, &parts[i], &parts[j]) {
+                    parts[i] = merged;
+                    parts.remove(j);
+                    continue 'merge;
+                }
+            }
+        }
+
+        break;
+    }
+
+    Some(parts)
+}
+
+/// Merges two parts that share an edge if the merged part would be convex
+///
+/// Both parts must have the same orientation.
+fn merge_convex_parts(points: &[Point2], part1: &[usize], part2: &[usize]) -> Option<Vec<usize>> {
+    fn edges(part: &[usize]) -> impl Iterator<Item=(usize, usize, usize)> + '_ {
+        let n = part.len();
+        (0..n).map(move |i| (i, part[i], part[(i + 1) % n]))
+    }
+
+    // Since the parts have the same orientation, a shared edge (a, b) in one
+    // part will appear as (b, a) in the other part
+    let (i1, i2) = edges(part1).find_map(|(i1, a, b)| {
+        edges(part2)
+            .find(|&(_, c, d)| a == d && b == c)
+            .map(|(i2, _, _)| (i1, i2))
+    })?;
+
+    // Start part1 after its shared edge (at b) so it ends with a, then add the
+    // vertices of part2 between a and b (exclusive)
+    let n1 = part1.len();
+    let n2 = part2.len();
+    let mut merged: Vec<_> = (0..n1).map(|k| part1[(i1 + 1 + k) % n1]).collect();
+    merged.extend((0..n2 - 2).map(|k| part2[(i2 + 2 + k) % n2]));
+
+    let merged_points: Vec<_> = merged.iter().map(|&i| points[i]).collect();
+    if is_convex(&merged_points) {
+        Some(merged)
+    } else {
+        None
+    }
+}
+
 impl fmt::Debug for Shape {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -144,3 +391,92 @@ impl fmt::Debug for Shape {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(coords: &[(f64, f64)]) -> Vec<Point2> {
+        coords.iter().map(|&(x, y)| Point2::new(x, y)).collect()
+    }
+
+    /// The vertices of a regular pentagram, visiting every second point of a
+    /// regular pentagon
+    fn pentagram() -> Vec<Point2> {
+        (0..5).map(|i| {
+            let angle = (i * 2) as f64 / 5.0 * 2.0 * std::f64::consts::PI;
+            Point2::new(angle.cos(), angle.sin())
+        }).collect()
+    }
+
+    fn l_shape() -> Vec<Point2> {
+        points(&[(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0)])
+    }
+
+    #[test]
+    fn is_convex_square() {
+        let square = points(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+        assert!(is_convex(&square));
+
+        let reversed: Vec<_> = square.into_iter().rev().collect();
+        assert!(is_convex(&reversed));
+    }
+
+    #[test]
+    fn is_convex_collinear_vertex() {
+        let square = points(&[(0.0, 0.0), (0.5, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+        assert!(is_convex(&square));
+    }
+
+    #[test]
+    fn is_convex_concave() {
+        assert!(!is_convex(&l_shape()));
+    }
+
+    #[test]
+    fn is_convex_pentagram() {
+        // Every turn of a pentagram is in the same direction, but its edges
+        // cross each other
+        assert!(!is_convex(&pentagram()));
+    }
+
+    #[test]
+    fn is_convex_bowtie() {
+        let bowtie = points(&[(0.0, 0.0), (1.0, 1.0), (1.0, 0.0), (0.0, 1.0)]);
+        assert!(!is_convex(&bowtie));
+    }
+
+    #[test]
+    fn convex_decomposition_convex() {
+        let square = points(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+        let parts = convex_decomposition(&square).unwrap();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].len(), 4);
+    }
+
+    #[test]
+    fn convex_decomposition_concave() {
+        for shape in [l_shape(), l_shape().into_iter().rev().collect()] {
+            let parts = convex_decomposition(&shape).unwrap();
+            assert_eq!(parts.len(), 2);
+
+            let mut total_area = 0.0;
+            for part in parts {
+                let part: Vec<_> = part.iter().map(|&i| shape[i]).collect();
+                assert!(is_convex(&part));
+                total_area += signed_area2(&part).abs() / 2.0;
+            }
+            assert!((total_area - 3.0).abs() < POLYGON_EPSILON);
+        }
+    }
+
+    #[test]
+    fn polygon_self_intersecting() {
+        assert!(Shape::polygon(&pentagram()).is_none());
+    }
+
+    #[test]
+    fn polygon_concave() {
+        assert!(matches!(Shape::polygon(&l_shape()), Some(Shape::Compound(_))));
+    }
+}
@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use crate::Vec2;
+
+/// Determines what an entity does once it reaches the end of its path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathMode {
+    /// Moves back along the path in the opposite direction
+    PingPong,
+    /// Moves from the last point straight back to the first point and starts
+    /// again
+    Loop,
+}
+
+/// Determines how the speed of an entity changes as it moves between two
+/// consecutive points on its path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Easing {
+    /// Moves at a constant speed
+    Linear,
+    /// Starts slowly and speeds up
+    EaseIn,
+    /// Starts quickly and slows down
+    EaseOut,
+    /// Starts slowly, speeds up, then slows down again
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps the fraction of the distance between two points that would be
+    /// covered at a constant speed to the fraction covered with this easing
+    ///
+    /// Both fractions are between 0.0 and 1.0.
+    pub fn apply(self, t: f64) -> f64 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// A sequence of points in world coordinates that entities can move along
+///
+/// Paths have no state of their own. The position along a path is always
+/// computed from the time elapsed since the level started. That means that
+/// every entity following the same path with the same settings moves in sync.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotionPath {
+    points: Vec<Vec2>,
+    /// true if the last point connects back to the first point
+    closed: bool,
+}
+
+impl MotionPath {
+    /// Creates a new path through the given points
+    ///
+    /// # Panics
+    ///
+    /// Panics if no points are provided
+    pub fn new(points: Vec<Vec2>, closed: bool) -> Self {
+        assert!(!points.is_empty(), "bug: a path must have at least one point");
+
+        Self {points, closed}
+    }
+
+    pub fn points(&self) -> &[Vec2] {
+        &self.points
+    }
+
+    /// Returns true if the last point of this path connects back to the first
+    /// point (e.g. a polygon)
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Returns the position on this path at the given time for an entity that
+    /// moves along it at the given speed
+    pub fn position_at(&self, elapsed: Duration, speed: f64, mode: PathMode, easing: Easing) -> Vec2 {
+        let segments = self.segments(mode);
+        let total_length: f64 = segments.iter()
+            .map(|&(start, end)| (end - start).norm())
+            .sum();
+
+        // Avoid dividing by zero if the entity can't go anywhere
+        if total_length == 0.0 || speed <= 0.0 {
+            return self.points[0];
+        }
+
+        let mut distance = (elapsed.as_secs_f64() * speed) % total_length;
+        for (start, end) in segments {
+            let length = (end - start).norm();
+            if distance < length {
+                return start + (end - start) * easing.apply(distance / length);
+            }
+            distance -= length;
+        }
+
+        // Only reachable because of floating point error. Every mode ends up
+        // back at the first point.
+        self.points[0]
+    }
+
+    /// Returns the start and end of each straight line that an entity moves
+    /// along during a single cycle of the given mode
+    fn segments(&self, mode: PathMode) -> Vec<(Vec2, Vec2)> {
+        let Self {points, closed} = self;
+
+        let mut segments: Vec<_> = points.windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect();
+
+        if *closed || mode == PathMode::Loop {
+            let first = points[0];
+            let last = points[points.len() - 1];
+            segments.push((last, first));
+        }
+
+        if mode == PathMode::PingPong {
+            let backward: Vec<_> = segments.iter().rev()
+                .map(|&(start, end)| (end, start))
+                .collect();
+            segments.extend(backward);
+        }
+
+        segments
+    }
+}
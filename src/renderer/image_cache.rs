@@ -1,4 +1,5 @@
 use std::fmt;
+use std::sync::Arc;
 use std::path::PathBuf;
 use std::collections::HashMap;
 
@@ -8,7 +9,7 @@ use sdl2::{
     image::LoadTexture,
 };
 
-use crate::{SdlError, ImageParams};
+use crate::{SdlError, ImageParams, SharedAssetSource};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ImageId(usize);
@@ -24,7 +25,9 @@ pub struct ImageId(usize);
 // user to preload paths that they know will definitely be loaded at some
 // point. Could also be a custom property in Tiled - `preload_image: true`
 struct CachedImage {
-    /// The path to the image file
+    /// The source that the image file is loaded from
+    source: SharedAssetSource,
+    /// The canonical path to the image file within its source
     path: PathBuf,
     /// The raw image loaded from the path on-demand
     base_image: Option<Texture>,
@@ -33,8 +36,9 @@ struct CachedImage {
 }
 
 impl CachedImage {
-    pub fn new(path: PathBuf) -> Self {
+    pub fn new(source: SharedAssetSource, path: PathBuf) -> Self {
         Self {
+            source,
             path,
             base_image: None,
             params_cache: HashMap::new(),
@@ -61,7 +65,9 @@ impl CachedImage {
         texture_creator: &mut TextureCreator<WindowContext>,
     ) -> Result<&mut Texture, SdlError> {
         if self.base_image.is_none() {
-            let tex = texture_creator.load_texture(&self.path)?;
+            let data = self.source.read(&self.path)
+                .map_err(|err| SdlError(format!("Error with path `{}`: {}", self.path.display(), err)))?;
+            let tex = texture_creator.load_texture_bytes(&data)?;
             self.base_image = Some(tex);
         }
 
@@ -71,6 +77,7 @@ impl CachedImage {
 
     pub fn invalidate(&mut self) {
         let CachedImage {
+            source: _,
             path: _,
             base_image,
             params_cache,
@@ -89,8 +96,10 @@ impl CachedImage {
 #[derive(Default)]
 pub struct ImageCache {
     texture_creator: Option<TextureCreator<WindowContext>>,
-    /// Map from the canonical path of an image file to its image ID
-    image_paths: HashMap<PathBuf, ImageId>,
+    /// Map from the source and canonical path of an image file to its image ID
+    ///
+    /// Sources are identified by the address of their shared data.
+    image_paths: HashMap<(usize, PathBuf), ImageId>,
     /// The value in `ImageId` indexes into this field
     images: Vec<CachedImage>,
 }
@@ -115,15 +124,18 @@ impl ImageCache {
     /// Adds an image to the cache and returns its ID. The image is not loaded
     /// until it is first used by the renderer.
     ///
-    /// If the image is already in the cache, its existing ID is returned.
-    pub fn add<P: Into<PathBuf>>(&mut self, path: P) -> ImageId {
+    /// The path must be the canonical path of the image within the given
+    /// source. If the image is already in the cache, its existing ID is
+    /// returned.
+    pub fn add<P: Into<PathBuf>>(&mut self, source: &SharedAssetSource, path: P) -> ImageId {
         let path = path.into();
-        match self.image_paths.get(&path) {
+        let key = (Arc::as_ptr(source) as *const () as usize, path);
+        match self.image_paths.get(&key) {
             Some(&id) => id,
             None => {
                 let id = ImageId(self.images.len());
-                self.images.push(CachedImage::new(path.clone()));
-                self.image_paths.insert(path, id);
+                self.images.push(CachedImage::new(source.clone(), key.1.clone()));
+                self.image_paths.insert(key, id);
                 id
             },
         }
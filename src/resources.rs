@@ -1,8 +1,61 @@
 mod physics_events;
+mod signal_events;
 
 pub use physics_events::*;
+pub use signal_events::*;
+
+use std::sync::Arc;
+use std::time::{Instant, Duration};
+use std::collections::HashMap;
 
 use sdl2::rect::Rect;
+use specs::Entity;
+
+use crate::{MotionPath, ToggleTiming};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Viewport(pub Rect);
+
+/// Keeps track of the time elapsed since the level started
+///
+/// All animations are based on this clock so they stay in sync.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    start: Instant,
+    elapsed: Duration,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            elapsed: Duration::default(),
+        }
+    }
+}
+
+impl Clock {
+    /// Updates the clock to the current time
+    pub fn tick(&mut self) {
+        self.elapsed = self.start.elapsed();
+    }
+
+    /// The time elapsed since the clock was created, as of the last tick
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+/// The paths created from objects with the `path` type, indexed by the ID of
+/// each object
+#[derive(Debug, Clone, Default)]
+pub struct MotionPaths(pub HashMap<u32, Arc<MotionPath>>);
+
+/// The timing shared by all entities with a `Toggle` component in each sync
+/// group, indexed by the name of the group
+#[derive(Debug, Clone, Default)]
+pub struct ToggleGroups(pub HashMap<String, ToggleTiming>);
+
+/// The entities created from tile objects, indexed by the ID of each object
+#[derive(Debug, Clone, Default)]
+pub struct ObjectEntities(pub HashMap<u32, Entity>);
@@ -138,4 +138,27 @@ impl CollisionsMap {
         let collisions1 = unsafe { &mut *collisions1 };
         (collisions1, collisions2)
     }
+
+    /// Removes every collision recorded between the given entity and any other
+    /// entity
+    ///
+    /// Used when the collider of an entity is replaced so that the collisions
+    /// of the old collider are not left behind.
+    pub fn remove_collisions_with(&mut self, entity: Entity) {
+        self.collisions.remove(&entity);
+
+        for collisions in self.collisions.values_mut() {
+            let Collisions {
+                touching_top,
+                touching_bottom,
+                touching_left,
+                touching_right,
+                intersecting,
+            } = collisions;
+
+            for colliders in [touching_top, touching_bottom, touching_left, touching_right, intersecting] {
+                colliders.retain(|&other| other != entity);
+            }
+        }
+    }
 }
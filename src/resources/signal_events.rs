@@ -0,0 +1,26 @@
+use specs::{shrev::EventChannel, Entity};
+
+/// The message sent to an entity when a signal is sent to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SignalMessage {
+    /// Switches the entity on if it is switched off, or off if it is switched
+    /// on
+    Toggle,
+    /// Switches the entity on
+    On,
+    /// Switches the entity off
+    Off,
+}
+
+/// Event for when a signal is sent to an entity
+///
+/// Entities that receive a signal are given a `Toggle` component if they do not
+/// already have one.
+#[derive(Debug, Clone)]
+pub struct SignalEvent {
+    /// The entity that the signal was sent to
+    pub target: Entity,
+    pub message: SignalMessage,
+}
+
+pub type SignalEvents = EventChannel<SignalEvent>;
@@ -1,5 +1,5 @@
 use std::path::{PathBuf, Path};
-use std::fs::File;
+use std::sync::Arc;
 use std::io;
 
 use serde::{Serialize, Deserialize};
@@ -10,12 +10,18 @@ use thiserror::Error;
 use crate::{
     CHARACTER_DRAW_ORDER,
     CharacterSprites,
+    Animation,
+    AnimationFrame,
+    TileImage,
     ImageCache,
     Image,
     Align,
     ImageParams,
     Sprite,
     Size,
+    Vec2,
+    SharedAssetSource,
+    filesystem_source,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -86,19 +92,47 @@ pub enum LoadSpritesError {
 
 #[derive(Debug)]
 pub struct CharacterSpritesheet {
+    /// The source that the spritesheet image is loaded from
+    source: SharedAssetSource,
+    /// The canonical path of the spritesheet image within its source
     spritesheet: PathBuf,
     config: SpritesheetConfig,
 }
 
 impl CharacterSpritesheet {
+    /// Opens the spritesheet image and config file at the given paths on the
+    /// filesystem
     pub fn open(spritesheet: &Path, config: &Path) -> Result<Self, LoadSpritesError> {
-        let spritesheet = spritesheet.to_path_buf();
-        let config = serde_json::from_reader(File::open(config)?)?;
-        Ok(Self {spritesheet, config})
+        Self::load_from(filesystem_source(), spritesheet, config)
+    }
+
+    /// Loads the spritesheet image and config file at the given paths within
+    /// the given asset source
+    pub fn load_from(
+        source: SharedAssetSource,
+        spritesheet: &Path,
+        config: &Path,
+    ) -> Result<Self, LoadSpritesError> {
+        let config = source.read(config)?;
+        Self::from_reader(source, spritesheet, &config[..])
+    }
+
+    /// Loads the spritesheet config from the given reader
+    ///
+    /// The spritesheet image is loaded from the given path within the asset
+    /// source.
+    pub fn from_reader<R: io::Read>(
+        source: SharedAssetSource,
+        spritesheet: &Path,
+        config: R,
+    ) -> Result<Self, LoadSpritesError> {
+        let spritesheet = source.canonicalize(spritesheet)?;
+        let config = serde_json::from_reader(config)?;
+        Ok(Self {source, spritesheet, config})
     }
 
     pub fn load(&self, image_cache: &mut ImageCache) -> Result<CharacterSprites, LoadSpritesError> {
-        let id = image_cache.add(&self.spritesheet);
+        let id = image_cache.add(&self.source, &self.spritesheet);
 
         let make_image = |src, size| {
             let src = Some(src);
@@ -110,7 +144,8 @@ impl CharacterSpritesheet {
                 angle: R64::new(0.0),
                 alpha: u8::MAX,
             };
-            Image {id, src, align, params}
+            let offset = Vec2::default();
+            Image {id, src, align, offset, params}
         };
 
         use SpritesheetConfig::*;
@@ -138,8 +173,30 @@ impl CharacterSpritesheet {
                     Sprite {image, align_size, pivot, draw_order}
                 };
 
+                let grid_animation = |frames: &[Frame]| {
+                    let frames: Vec<_> = frames.iter().map(|&Frame {row, col, duration}| {
+                        let Sprite {image, ..} = grid_sprite(CellPos {row, col});
+                        let image = TileImage {
+                            id: image.id,
+                            src: image.src,
+                            size: image.params.size,
+                            align: image.align,
+                            offset: image.offset,
+                        };
+
+                        AnimationFrame {image, duration}
+                    }).collect();
+
+                    if frames.is_empty() {
+                        None
+                    } else {
+                        Some(Arc::new(Animation::new(frames)))
+                    }
+                };
+
                 Ok(CharacterSprites {
                     idle: poses.idle.map(grid_sprite),
+                    climb: animations.climb.as_deref().and_then(grid_animation),
                 })
             }
         }
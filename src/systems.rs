@@ -2,7 +2,15 @@ mod physics;
 mod keyboard;
 mod viewport_updater;
 mod collision_detector;
-mod currency_system;
+mod path_follower;
+mod switch_system;
+mod toggle_system;
+mod pickup_system;
+mod combat_system;
+mod door_system;
+mod jump_pad_system;
+mod character_animator;
+mod sprite_animator;
 
 use specs::{World, System};
 
@@ -12,7 +20,15 @@ pub struct Systems {
     pub physics: physics::Physics,
     pub collision_detector: collision_detector::CollisionsDetector,
     pub viewport_updater: viewport_updater::ViewportUpdater,
-    pub currency_system: currency_system::CurrencySystem,
+    pub path_follower: path_follower::PathFollower,
+    pub switch_system: switch_system::SwitchSystem,
+    pub toggle_system: toggle_system::ToggleSystem,
+    pub pickup_system: pickup_system::PickupSystem,
+    pub combat_system: combat_system::CombatSystem,
+    pub door_system: door_system::DoorSystem,
+    pub jump_pad_system: jump_pad_system::JumpPadSystem,
+    pub character_animator: character_animator::CharacterAnimator,
+    pub sprite_animator: sprite_animator::SpriteAnimator,
 }
 
 impl Systems {
@@ -22,14 +38,30 @@ impl Systems {
             physics,
             collision_detector,
             viewport_updater,
-            currency_system,
+            path_follower,
+            switch_system,
+            toggle_system,
+            pickup_system,
+            combat_system,
+            door_system,
+            jump_pad_system,
+            character_animator,
+            sprite_animator,
         } = self;
 
         keyboard.setup(world);
         physics.setup(world);
         collision_detector.setup(world);
         viewport_updater.setup(world);
-        currency_system.setup(world);
+        path_follower.setup(world);
+        switch_system.setup(world);
+        toggle_system.setup(world);
+        pickup_system.setup(world);
+        combat_system.setup(world);
+        door_system.setup(world);
+        jump_pad_system.setup(world);
+        character_animator.setup(world);
+        sprite_animator.setup(world);
     }
 
     pub fn run(&mut self, world: &World) {
@@ -38,7 +70,15 @@ impl Systems {
             physics,
             collision_detector,
             viewport_updater,
-            currency_system,
+            path_follower,
+            switch_system,
+            toggle_system,
+            pickup_system,
+            combat_system,
+            door_system,
+            jump_pad_system,
+            character_animator,
+            sprite_animator,
         } = self;
 
         keyboard.run(world.system_data());
@@ -49,6 +89,14 @@ impl Systems {
             || viewport_updater.run(world.system_data()),
         );
 
-        currency_system.run(world.system_data());
+        path_follower.run(world.system_data());
+        switch_system.run(world.system_data());
+        toggle_system.run(world.system_data());
+        pickup_system.run(world.system_data());
+        combat_system.run(world.system_data());
+        door_system.run(world.system_data());
+        jump_pad_system.run(world.system_data());
+        character_animator.run(world.system_data());
+        sprite_animator.run(world.system_data());
     }
 }
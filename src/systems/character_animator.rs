@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use specs::{System, SystemData, World, Entities, ReadStorage, WriteStorage, Join, prelude::ResourceId};
+
+use crate::{CharacterSprites, Climbing, AnimatedSprite, Sprite};
+
+#[derive(SystemData)]
+pub struct Data<'a> {
+    pub entities: Entities<'a>,
+    pub character_sprites: ReadStorage<'a, CharacterSprites>,
+    pub climbings: ReadStorage<'a, Climbing>,
+    pub animated_sprites: WriteStorage<'a, AnimatedSprite>,
+    pub sprites: WriteStorage<'a, Sprite>,
+}
+
+/// Switches the sprites of characters to match what they are currently doing
+#[derive(Debug, Default)]
+pub struct CharacterAnimator;
+
+impl<'a> System<'a> for CharacterAnimator {
+    type SystemData = Data<'a>;
+
+    fn run(&mut self, data: Self::SystemData) {
+        let Data {
+            entities,
+            character_sprites,
+            climbings,
+            mut animated_sprites,
+            mut sprites,
+        } = data;
+
+        for (entity, character_sprites) in (&entities, &character_sprites).join() {
+            let climb = match &character_sprites.climb {
+                Some(climb) => climb,
+                None => continue,
+            };
+
+            let playing_climb = animated_sprites.get(entity)
+                .map(|AnimatedSprite(animation)| Arc::ptr_eq(animation, climb))
+                .unwrap_or(false);
+
+            if climbings.contains(entity) {
+                if !playing_climb {
+                    animated_sprites.insert(entity, AnimatedSprite(climb.clone()))
+                        .expect("bug: unable to insert animated sprite component");
+                }
+
+            } else if playing_climb {
+                animated_sprites.remove(entity);
+                // Return to the default sprite now that the animation is done
+                if let Some(sprite) = character_sprites.default_sprite() {
+                    sprites.insert(entity, sprite)
+                        .expect("bug: unable to insert sprite component");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use specs::{System, SystemData, World, Read, Entities, ReadStorage, WriteStorage, Join, prelude::ResourceId};
+
+use crate::{
+    Clock,
+    CollisionsMap,
+    Collisions,
+    Damage,
+    Health,
+    Invincible,
+    KnockedBack,
+    PhysicsBody,
+    PhysicsCollider,
+    Position,
+    Isometry,
+    Vec2,
+};
+
+/// The amount of time after being knocked back that an entity cannot be
+/// controlled
+const KNOCKBACK_DURATION: Duration = Duration::from_millis(300);
+
+#[derive(SystemData)]
+pub struct Data<'a> {
+    pub clock: Read<'a, Clock>,
+    pub collisions: Read<'a, CollisionsMap>,
+    pub entities: Entities<'a>,
+    pub positions: ReadStorage<'a, Position>,
+    pub physics_colliders: ReadStorage<'a, PhysicsCollider>,
+    pub damages: ReadStorage<'a, Damage>,
+    pub healths: WriteStorage<'a, Health>,
+    pub invincibles: WriteStorage<'a, Invincible>,
+    pub knocked_backs: WriteStorage<'a, KnockedBack>,
+    pub physics_bodies: WriteStorage<'a, PhysicsBody>,
+}
+
+/// Damages entities with a `Health` component when they collide with an entity
+/// that has a `Damage` component
+#[derive(Debug, Default)]
+pub struct CombatSystem;
+
+impl<'a> System<'a> for CombatSystem {
+    type SystemData = Data<'a>;
+
+    fn run(&mut self, data: Self::SystemData) {
+        let Data {
+            clock,
+            collisions,
+            entities,
+            positions,
+            physics_colliders,
+            damages,
+            mut healths,
+            mut invincibles,
+            mut knocked_backs,
+            mut physics_bodies,
+        } = data;
+
+        let now = clock.elapsed();
+
+        // Entities can be hit again once their invincibility runs out
+        let expired: Vec<_> = (&entities, &invincibles).join()
+            .filter(|&(_, &Invincible {until})| until <= now)
+            .map(|(entity, _)| entity)
+            .collect();
+        for entity in expired {
+            invincibles.remove(entity);
+        }
+
+        let recovered: Vec<_> = (&entities, &knocked_backs).join()
+            .filter(|&(_, &KnockedBack {until})| until <= now)
+            .map(|(entity, _)| entity)
+            .collect();
+        for entity in recovered {
+            knocked_backs.remove(entity);
+        }
+
+        // Find every entity that was hit along with the entity that hit it
+        let hits: Vec<_> = (&entities, &healths, !&invincibles).join()
+            .filter_map(|(entity, _, ())| {
+                colliding_entities(collisions.get(entity))
+                    .find(|&other| damages.contains(other))
+                    .map(|hazard| (entity, hazard))
+            })
+            .collect();
+
+        for (entity, hazard) in hits {
+            let &Damage {amount, knockback, invincibility} = damages.get(hazard)
+                .expect("bug: hazard should have a damage component");
+
+            let Health(health) = healths.get_mut(entity)
+                .expect("bug: entity should have a health component");
+            *health = health.saturating_sub(amount);
+
+            // Push the entity away from the hazard so it doesn't immediately
+            // get hit again
+            let body = physics_bodies.get_mut(entity).filter(|_| knockback != 0.0);
+            if let Some(body) = body {
+                let entity_center = center(entity, &positions, &physics_colliders);
+                let hazard_center = center(hazard, &positions, &physics_colliders);
+                let direction = match (entity_center, hazard_center) {
+                    (Some(entity_center), Some(hazard_center)) => {
+                        (entity_center - hazard_center).try_normalize(f64::EPSILON)
+                    },
+                    _ => None,
+                };
+                // Push the entity upwards if there is no clear direction
+                let direction = direction.unwrap_or_else(|| Vec2::new(0.0, -1.0));
+
+                body.velocity.linear = direction * knockback;
+
+                // Stop the controls of the entity from immediately cancelling
+                // out the knockback
+                knocked_backs.insert(entity, KnockedBack {until: now + KNOCKBACK_DURATION})
+                    .expect("bug: unable to insert knocked back component");
+            }
+
+            // Saturate so that a huge invincibility time means "forever" rather
+            // than overflowing
+            invincibles.insert(entity, Invincible {until: now.saturating_add(invincibility)})
+                .expect("bug: unable to insert invincible component");
+        }
+    }
+}
+
+/// Iterates over every entity touching or intersecting with an entity
+fn colliding_entities(collisions: &Collisions) -> impl Iterator<Item=specs::Entity> + '_ {
+    let Collisions {
+        touching_top,
+        touching_bottom,
+        touching_left,
+        touching_right,
+        intersecting,
+    } = collisions;
+
+    touching_top.iter()
+        .chain(touching_bottom)
+        .chain(touching_left)
+        .chain(touching_right)
+        .chain(intersecting)
+        .copied()
+}
+
+/// Returns the center of the collider of an entity in world coordinates, or
+/// its position if it has no collider
+fn center(
+    entity: specs::Entity,
+    positions: &ReadStorage<Position>,
+    physics_colliders: &ReadStorage<PhysicsCollider>,
+) -> Option<Vec2> {
+    let &Position(pos) = positions.get(entity)?;
+
+    Some(match physics_colliders.get(entity) {
+        Some(PhysicsCollider {shape, offset, ..}) => {
+            let bounds = shape.bounds().transform_by(&Isometry::new(pos + offset, 0.0));
+            bounds.center().coords
+        },
+
+        None => pos,
+    })
+}
@@ -0,0 +1,88 @@
+use specs::{System, SystemData, World, Write, Entities, WriteStorage, Join, prelude::ResourceId};
+
+use crate::{CollisionsMap, Collisions, Door, Inventory, PhysicsCollider, Sprite};
+
+#[derive(SystemData)]
+pub struct Data<'a> {
+    pub collisions: Write<'a, CollisionsMap>,
+    pub entities: Entities<'a>,
+    pub doors: WriteStorage<'a, Door>,
+    pub inventories: WriteStorage<'a, Inventory>,
+    pub physics_colliders: WriteStorage<'a, PhysicsCollider>,
+    pub sprites: WriteStorage<'a, Sprite>,
+}
+
+/// Opens doors when they are touched by an entity holding the required item
+#[derive(Debug, Default)]
+pub struct DoorSystem;
+
+impl<'a> System<'a> for DoorSystem {
+    type SystemData = Data<'a>;
+
+    fn run(&mut self, data: Self::SystemData) {
+        let Data {
+            mut collisions,
+            entities,
+            mut doors,
+            mut inventories,
+            mut physics_colliders,
+            mut sprites,
+        } = data;
+
+        let mut opened = Vec::new();
+        for (entity, inventory) in (&entities, &mut inventories).join() {
+            let Collisions {
+                touching_top,
+                touching_bottom,
+                touching_left,
+                touching_right,
+                intersecting: _,
+            } = collisions.get(entity);
+
+            let touching = touching_top.iter()
+                .chain(touching_bottom)
+                .chain(touching_left)
+                .chain(touching_right);
+            for &other in touching {
+                let door = match doors.get_mut(other) {
+                    Some(door) if !door.open => door,
+                    _ => continue,
+                };
+
+                if let Some(required_item) = &door.required_item {
+                    if inventory.count(required_item) == 0 {
+                        continue;
+                    }
+
+                    if door.consume_item {
+                        inventory.remove(required_item, 1);
+                    }
+                }
+
+                door.open = true;
+                opened.push(other);
+            }
+        }
+
+        for door in opened {
+            // The sensor property of an existing collider cannot be changed, so
+            // the collider is re-added without a handle to have the physics
+            // system replace it
+            if let Some(collider) = physics_colliders.get(door) {
+                let collider = PhysicsCollider {
+                    handle: None,
+                    sensor: true,
+                    ..collider.clone()
+                };
+                physics_colliders.insert(door, collider)
+                    .expect("bug: unable to insert physics collider component");
+                collisions.remove_collisions_with(door);
+            }
+
+            let open_image = doors.get(door).and_then(|door| door.open_image.clone());
+            if let (Some(open_image), Some(sprite)) = (open_image, sprites.get_mut(door)) {
+                sprite.image = open_image;
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use specs::{System, SystemData, World, Read, Entities, ReadStorage, WriteStorage, Join, prelude::ResourceId};
+
+use crate::{
+    Clock,
+    CollisionsMap,
+    JumpPad,
+    JumpPadLaunch,
+    JumpPadPressed,
+    PlatformerControls,
+    PhysicsBody,
+    Sprite,
+};
+
+#[derive(SystemData)]
+pub struct Data<'a> {
+    pub clock: Read<'a, Clock>,
+    pub collisions: Read<'a, CollisionsMap>,
+    pub entities: Entities<'a>,
+    pub jump_pads: ReadStorage<'a, JumpPad>,
+    pub platformer_controls: ReadStorage<'a, PlatformerControls>,
+    pub jump_pads_pressed: WriteStorage<'a, JumpPadPressed>,
+    pub sprites: WriteStorage<'a, Sprite>,
+    pub physics_bodies: WriteStorage<'a, PhysicsBody>,
+}
+
+/// Launches entities upward when they land on top of an entity with a `JumpPad`
+/// component
+#[derive(Debug, Default)]
+pub struct JumpPadSystem;
+
+impl<'a> System<'a> for JumpPadSystem {
+    type SystemData = Data<'a>;
+
+    fn run(&mut self, data: Self::SystemData) {
+        let Data {
+            clock,
+            collisions,
+            entities,
+            jump_pads,
+            platformer_controls,
+            mut jump_pads_pressed,
+            mut sprites,
+            mut physics_bodies,
+        } = data;
+
+        let now = clock.elapsed();
+
+        // Jump pads go back to their original image once their pressed time
+        // runs out
+        let released: Vec<_> = (&entities, &jump_pads_pressed).join()
+            .filter(|(_, pressed)| pressed.until <= now)
+            .map(|(entity, _)| entity)
+            .collect();
+        for jump_pad in released {
+            let JumpPadPressed {until: _, released_image} = jump_pads_pressed.remove(jump_pad)
+                .expect("bug: jump pad should have a pressed component");
+            if let Some(sprite) = sprites.get_mut(jump_pad) {
+                sprite.image = released_image;
+            }
+        }
+
+        for (entity, body) in (&entities, &mut physics_bodies).join() {
+            // An entity has landed on a jump pad if the jump pad is below it
+            let jump_pad = collisions.get(entity).touching_bottom.iter()
+                .copied()
+                .find(|&other| jump_pads.contains(other));
+            let jump_pad = match jump_pad {
+                Some(jump_pad) => jump_pad,
+                None => continue,
+            };
+
+            let JumpPad {launch, ref pressed_image, pressed_duration} = *jump_pads.get(jump_pad)
+                .expect("bug: jump pad should have a jump pad component");
+
+            // Note: the y-axis goes down, so upward velocities are negative
+            body.velocity.linear.y = match launch {
+                JumpPadLaunch::Speed(speed) => -speed,
+                JumpPadLaunch::JumpMultiplier(multiplier) => match platformer_controls.get(entity) {
+                    Some(controls) => controls.jump_velocity * multiplier,
+                    None => continue,
+                },
+            };
+
+            // A huge pressed time keeps the jump pad pressed rather than
+            // overflowing
+            let until = now.saturating_add(pressed_duration);
+            match jump_pads_pressed.get_mut(jump_pad) {
+                // Keep the pressed image for longer if the jump pad is still
+                // pressed from a previous launch
+                Some(pressed) => pressed.until = until,
+
+                None => if let (Some(pressed_image), Some(sprite)) = (pressed_image, sprites.get_mut(jump_pad)) {
+                    let released_image = std::mem::replace(&mut sprite.image, pressed_image.clone());
+                    jump_pads_pressed.insert(jump_pad, JumpPadPressed {until, released_image})
+                        .expect("bug: unable to insert jump pad pressed component");
+                },
+            }
+        }
+    }
+}
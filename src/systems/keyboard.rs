@@ -1,8 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use specs::{System, SystemData, World, Read, ReadExpect, Entities, ReadStorage, WriteStorage, Join, prelude::ResourceId};
 
-use crate::{PhysicsBody, PlatformerControls, EventStream, EventKind, Key, CollisionsMap};
+use crate::{PhysicsBody, PlatformerControls, Ladder, Climbing, DroppingDown, Interacting, KnockedBack, EventStream, EventKind, Key, CollisionsMap};
 
 #[derive(SystemData)]
 pub struct Data<'a> {
@@ -10,6 +10,11 @@ pub struct Data<'a> {
     pub collisions: Read<'a, CollisionsMap>,
     pub entities: Entities<'a>,
     pub platformer_controls: ReadStorage<'a, PlatformerControls>,
+    pub ladders: ReadStorage<'a, Ladder>,
+    pub knocked_backs: ReadStorage<'a, KnockedBack>,
+    pub climbings: WriteStorage<'a, Climbing>,
+    pub dropping_down: WriteStorage<'a, DroppingDown>,
+    pub interactings: WriteStorage<'a, Interacting>,
     pub physics_bodies: WriteStorage<'a, PhysicsBody>,
 }
 
@@ -21,6 +26,8 @@ struct KeyboardState {
     /// Assumes key is currently released if no events for that key have been
     /// observed
     key_is_pressed: HashMap<Key, bool>,
+    /// The keys that were pressed during the last update
+    key_just_pressed: HashSet<Key>,
 }
 
 impl KeyboardState {
@@ -29,13 +36,21 @@ impl KeyboardState {
         self.key_is_pressed.get(&key).copied().unwrap_or_default()
     }
 
+    /// Returns true if the given key was pressed during the last update
+    pub fn is_key_just_pressed(&self, key: Key) -> bool {
+        self.key_just_pressed.contains(&key)
+    }
+
     /// Updates the current state of keys based on the given events
     pub fn update(&mut self, events: &EventStream) {
+        self.key_just_pressed.clear();
+
         for event in events {
             use EventKind::*;
             match event.kind() {
                 &KeyDown {key, repeat: false, ..} => {
                     self.key_is_pressed.insert(key, true);
+                    self.key_just_pressed.insert(key);
                 },
 
                 &KeyUp {key, ..} => {
@@ -64,6 +79,11 @@ impl<'a> System<'a> for Keyboard {
             collisions,
             entities,
             platformer_controls,
+            ladders,
+            knocked_backs,
+            mut climbings,
+            mut dropping_down,
+            mut interactings,
             mut physics_bodies,
         } = data;
 
@@ -75,36 +95,101 @@ impl<'a> System<'a> for Keyboard {
                 left_key,
                 right_key,
                 jump_key,
+                climb_up_key,
+                climb_down_key,
+                drop_down_key,
+                interact_key,
                 horizontal_velocity,
                 jump_velocity,
                 midair_horizontal_multiplier,
+                climb_velocity,
             } = controls;
 
             let left_pressed = self.keyboard_state.is_key_pressed(left_key);
             let right_pressed = self.keyboard_state.is_key_pressed(right_key);
             let jump_pressed = self.keyboard_state.is_key_pressed(jump_key);
+            let climb_up_pressed = self.keyboard_state.is_key_pressed(climb_up_key);
+            let climb_down_pressed = self.keyboard_state.is_key_pressed(climb_down_key);
+            let drop_down_pressed = self.keyboard_state.is_key_pressed(drop_down_key);
+            // Holding the interact key should only interact once
+            let interact_pressed = self.keyboard_state.is_key_just_pressed(interact_key);
 
             let collisions = collisions.get(entity);
             let touching_ground = !collisions.touching_bottom.is_empty();
 
+            let on_ladder = collisions.intersecting.iter()
+                .any(|&other| ladders.contains(other));
+            let was_climbing = climbings.contains(entity);
+            let climbing = match climbings.get(entity) {
+                // Stop climbing when the entity leaves the ladder or jumps
+                Some(&Climbing {gravity_enabled}) if !on_ladder || jump_pressed => {
+                    body.gravity_enabled = gravity_enabled;
+                    climbings.remove(entity);
+                    false
+                },
+
+                Some(_) => true,
+
+                None if on_ladder && climb_velocity != 0.0 && (climb_up_pressed || climb_down_pressed) => {
+                    climbings.insert(entity, Climbing {gravity_enabled: body.gravity_enabled})
+                        .expect("bug: unable to insert climbing component");
+                    body.gravity_enabled = false;
+                    true
+                },
+
+                None => false,
+            };
+
             // Potentially change movement in midair
-            let hori_multiplier = if touching_ground {
+            let hori_multiplier = if touching_ground || climbing {
                 1.0
             } else {
                 midair_horizontal_multiplier
             };
 
-            // If neither is pressed or both are pressed, set speed to zero
-            if left_pressed == right_pressed {
-                body.velocity.linear.x = 0.0;
-            } else if left_pressed {
-                body.velocity.linear.x = -horizontal_velocity * hori_multiplier;
-            } else if right_pressed {
-                body.velocity.linear.x = horizontal_velocity * hori_multiplier;
+            // The entity keeps moving in the direction it was knocked back in
+            // until it recovers
+            if !knocked_backs.contains(entity) {
+                // If neither is pressed or both are pressed, set speed to zero
+                if left_pressed == right_pressed {
+                    body.velocity.linear.x = 0.0;
+                } else if left_pressed {
+                    body.velocity.linear.x = -horizontal_velocity * hori_multiplier;
+                } else if right_pressed {
+                    body.velocity.linear.x = horizontal_velocity * hori_multiplier;
+                }
+            }
+
+            if climbing {
+                // If neither is pressed or both are pressed, stay in place
+                if climb_up_pressed == climb_down_pressed {
+                    body.velocity.linear.y = 0.0;
+                } else if climb_up_pressed {
+                    body.velocity.linear.y = -climb_velocity;
+                } else if climb_down_pressed {
+                    body.velocity.linear.y = climb_velocity;
+                }
+            }
+
+            // The physics system lets the entity fall through one-way colliders
+            // for as long as the drop down key is held
+            if drop_down_pressed {
+                dropping_down.insert(entity, DroppingDown)
+                    .expect("bug: unable to insert dropping down component");
+            } else {
+                dropping_down.remove(entity);
+            }
+
+            if interact_pressed {
+                interactings.insert(entity, Interacting)
+                    .expect("bug: unable to insert interacting component");
+            } else {
+                interactings.remove(entity);
             }
 
-            // Only jump if currently touching the ground
-            if jump_pressed && touching_ground {
+            // Only jump if currently touching the ground or jumping off of a
+            // ladder
+            if jump_pressed && (touching_ground || was_climbing) {
                 body.velocity.linear.y = jump_velocity;
             }
         }
@@ -0,0 +1,79 @@
+use std::time::Duration;
+use std::collections::HashMap;
+
+use specs::{System, SystemData, World, Read, Entities, ReadStorage, WriteStorage, Join, prelude::ResourceId};
+
+use crate::{Clock, CollisionsMap, FollowPath, Position, PhysicsBody, Carried};
+
+use super::physics::TIMESTEP;
+
+#[derive(SystemData)]
+pub struct Data<'a> {
+    pub clock: Read<'a, Clock>,
+    pub collisions: Read<'a, CollisionsMap>,
+    pub entities: Entities<'a>,
+    pub follow_paths: ReadStorage<'a, FollowPath>,
+    pub positions: ReadStorage<'a, Position>,
+    pub physics_bodies: WriteStorage<'a, PhysicsBody>,
+    pub carried: WriteStorage<'a, Carried>,
+}
+
+/// Moves entities with a `FollowPath` component along their path and carries
+/// any entities standing on top of them along with them
+///
+/// Both are moved by setting velocities, so that the physics engine stops
+/// anything that runs into a solid collider along the way.
+#[derive(Debug, Default)]
+pub struct PathFollower;
+
+impl<'a> System<'a> for PathFollower {
+    type SystemData = Data<'a>;
+
+    fn run(&mut self, data: Self::SystemData) {
+        let Data {
+            clock,
+            collisions,
+            entities,
+            follow_paths,
+            positions,
+            mut physics_bodies,
+            mut carried,
+        } = data;
+
+        // Each entity aims for where it should be on its path once the next
+        // step of the physics engine has run
+        let next_elapsed = clock.elapsed() + Duration::from_secs_f64(TIMESTEP);
+
+        let mut moving = Vec::new();
+        for (entity, follow_path, &Position(pos), body) in (&entities, &follow_paths, &positions, &mut physics_bodies).join() {
+            let velocity = (follow_path.position_at(next_elapsed) - pos) / TIMESTEP;
+            body.velocity.linear = velocity;
+
+            if velocity.x != 0.0 || velocity.y != 0.0 {
+                moving.push((entity, velocity));
+            }
+        }
+
+        // A rider standing across several entities that follow the same path
+        // (e.g. a platform made of many tiles) must only be carried once
+        let mut rider_velocities = HashMap::new();
+        for (platform, velocity) in moving {
+            for &rider in &collisions.get(platform).touching_top {
+                // Entities following a path are only ever moved by that path
+                if follow_paths.contains(rider) || !physics_bodies.contains(rider) {
+                    continue;
+                }
+
+                rider_velocities.entry(rider).or_insert(velocity);
+            }
+        }
+
+        // Riders are carried for the next step only, so anything that is no
+        // longer standing on a moving entity stops being carried
+        carried.clear();
+        for (rider, velocity) in rider_velocities {
+            carried.insert(rider, Carried(velocity))
+                .expect("bug: unable to insert carried component");
+        }
+    }
+}
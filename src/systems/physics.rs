@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::ops::Range;
+use std::collections::{HashMap, HashSet};
 
 use specs::{
     System,
@@ -8,6 +9,7 @@ use specs::{
     Entity,
     Entities,
     Write,
+    ReadStorage,
     WriteStorage,
     Join,
     ReaderId,
@@ -22,6 +24,7 @@ use nphysics2d::{
         DefaultBodyHandle,
         DefaultColliderHandle,
         BodyPartHandle,
+        BodyStatus,
         Ground,
     },
     force_generator::DefaultForceGeneratorSet,
@@ -36,8 +39,20 @@ use nphysics2d::{
     },
 };
 
-use crate::math::Vec2;
-use crate::{Position, PhysicsBody, PhysicsCollider, Isometry, ContactEvents, ProximityEvents, ContactType, ContactEvent, ProximityEvent};
+use crate::math::{Vec2, Aabb};
+use crate::{Position, PhysicsBody, PhysicsCollider, DroppingDown, Carried, Isometry, ContactEvents, ProximityEvents, ContactType, ContactEvent, ProximityEvent};
+
+/// The distance (in world coordinates) that the bottom of a body may be below
+/// the top of a one-way collider and still land on it
+const ONE_WAY_TOLERANCE: f64 = 0.5;
+/// The speed (in world coordinates per second) that a body must be moving
+/// upward at to pass through one-way colliders
+const ONE_WAY_MIN_UPWARD_SPEED: f64 = 1.0;
+/// The amount of time (in seconds) simulated by each step of the physics engine
+pub(super) const TIMESTEP: f64 = 1.0 / 60.0;
+/// The collision groups used to decide which bodies pass through each one-way
+/// collider
+const ONE_WAY_BODY_GROUPS: Range<usize> = PhysicsCollider::ONE_WAY_BODY_GROUPS;
 
 #[derive(SystemData)]
 pub struct Data<'a> {
@@ -45,6 +60,8 @@ pub struct Data<'a> {
     pub positions: WriteStorage<'a, Position>,
     pub physics_bodies: WriteStorage<'a, PhysicsBody>,
     pub physics_colliders: WriteStorage<'a, PhysicsCollider>,
+    pub dropping_down: ReadStorage<'a, DroppingDown>,
+    pub carried: ReadStorage<'a, Carried>,
     pub contact_events: Write<'a, ContactEvents>,
     pub proximity_events: Write<'a, ProximityEvents>,
 }
@@ -60,6 +77,9 @@ pub struct Physics {
 
     body_handles: HashMap<Index, DefaultBodyHandle>,
     collider_handles: HashMap<Index, DefaultColliderHandle>,
+    /// The collision group in `ONE_WAY_BODY_GROUPS` assigned to each body that
+    /// can land on one-way colliders
+    one_way_body_groups: HashMap<Index, usize>,
 
     positions_reader_id: Option<ReaderId<ComponentEvent>>,
     physics_bodies_reader_id: Option<ReaderId<ComponentEvent>>,
@@ -76,8 +96,11 @@ impl Default for Physics {
         let mut bodies = DefaultBodySet::new();
         let ground = bodies.insert(Ground::new());
 
+        let mut mechanical_world = DefaultMechanicalWorld::new(Vec2::new(0.0, 0.0));
+        mechanical_world.set_timestep(TIMESTEP);
+
         Self {
-            mechanical_world: DefaultMechanicalWorld::new(Vec2::new(0.0, 0.0)),
+            mechanical_world,
             geometrical_world: DefaultGeometricalWorld::new(),
             bodies,
             colliders: DefaultColliderSet::new(),
@@ -87,6 +110,7 @@ impl Default for Physics {
 
             body_handles: HashMap::new(),
             collider_handles: HashMap::new(),
+            one_way_body_groups: HashMap::new(),
 
             positions_reader_id: None,
             physics_bodies_reader_id: None,
@@ -124,6 +148,7 @@ impl<'a> System<'a> for Physics {
 
             body_handles,
             collider_handles,
+            one_way_body_groups,
 
             positions_reader_id,
             physics_bodies_reader_id,
@@ -140,6 +165,8 @@ impl<'a> System<'a> for Physics {
             mut positions,
             mut physics_bodies,
             mut physics_colliders,
+            dropping_down,
+            carried,
             mut contact_events,
             mut proximity_events,
         } = data;
@@ -183,9 +210,22 @@ impl<'a> System<'a> for Physics {
             body_handles,
             *ground,
         );
+        // Needs the colliders to be fully synced so they all have handles
+        sync_one_way_colliders(
+            &entities,
+            &positions,
+            &physics_bodies,
+            &physics_colliders,
+            &dropping_down,
+            colliders,
+            one_way_body_groups,
+        );
 
         // Run the next step of the simulation
 
+        // Carried bodies only move with whatever is carrying them during this
+        // step, so that velocity is removed again right after
+        add_carried_velocities(&physics_bodies, &carried, bodies, 1.0);
         mechanical_world.step(
             geometrical_world,
             bodies,
@@ -193,6 +233,7 @@ impl<'a> System<'a> for Physics {
             joint_constraints,
             force_generators
         );
+        add_carried_velocities(&physics_bodies, &carried, bodies, -1.0);
 
         // Write contact events
         contact_events.iter_write(geometrical_world.contact_events().iter().map(|&event| {
@@ -463,6 +504,172 @@ fn sync_physics_colliders_to_engine(
     }
 }
 
+/// A body that can pass through or land on one-way colliders
+struct OneWayBody {
+    /// The collision group assigned to the collider of this body
+    group: usize,
+    bounds: Aabb,
+    /// true if the body is moving upward or dropping down, in which case it
+    /// passes through every one-way collider
+    passing_through: bool,
+}
+
+/// Allows bodies to pass through one-way colliders
+///
+/// The decision is made separately for every pair of body and one-way
+/// collider. Each dynamic body is made a member of one of the
+/// `ONE_WAY_BODY_GROUPS` and each one-way collider blacklists the group of every
+/// body passing through it. The group of a body never changes while the body
+/// exists, since changing it would recompute its contacts and could snap a body
+/// that is passing through a one-way collider onto it. Changing the collision
+/// groups of a collider makes the engine recompute its contacts, so any
+/// contacts with one-way colliders are stopped (generating the usual contact
+/// events) as soon as a body starts passing through them. This keeps the
+/// `CollisionsMap` accurate.
+fn sync_one_way_colliders(
+    entities: &Entities,
+    positions: &WriteStorage<Position>,
+    physics_bodies: &WriteStorage<PhysicsBody>,
+    physics_colliders: &WriteStorage<PhysicsCollider>,
+    dropping_down: &ReadStorage<DroppingDown>,
+    colliders: &mut DefaultColliderSet<f64>,
+    body_groups: &mut HashMap<Index, usize>,
+) {
+    let bodies: Vec<_> = (entities, positions, physics_bodies, physics_colliders).join()
+        .filter(|(_, _, body, physics_collider)| {
+            // Colliders that are never stopped by one-way colliders are left
+            // alone
+            body.body_status == BodyStatus::Dynamic &&
+                !physics_collider.one_way && !physics_collider.sensor &&
+                !physics_collider.collision_groups.is_group_blacklisted(PhysicsCollider::ONE_WAY_COLLISION_GROUP)
+        })
+        .collect();
+
+    // Free up the groups of any bodies that are gone
+    let body_ids: HashSet<_> = bodies.iter().map(|(entity, ..)| entity.id()).collect();
+    body_groups.retain(|id, _| body_ids.contains(id));
+    let mut group_sizes = [0usize; ONE_WAY_BODY_GROUPS.end];
+    for &group in body_groups.values() {
+        group_sizes[group] += 1;
+    }
+
+    let mut one_way_bodies = Vec::new();
+    for (entity, &Position(pos), body, physics_collider) in bodies {
+        // New bodies get the least used group, so bodies only share a group if
+        // there are more bodies than groups
+        let group = *body_groups.entry(entity.id()).or_insert_with(|| {
+            let group = ONE_WAY_BODY_GROUPS.min_by_key(|&group| group_sizes[group])
+                .expect("bug: there should be at least one one-way body group");
+            group_sizes[group] += 1;
+            group
+        });
+
+        let handle = physics_collider.handle
+            .expect("bug: all colliders should have handles at this point");
+        let collider = colliders.get_mut(handle)
+            .expect("bug: invalid physics collider handle");
+        let mut collision_groups = *collider.collision_groups();
+        // Only update when necessary since changing the collision groups
+        // causes every contact of the collider to be recomputed
+        let mut groups = ONE_WAY_BODY_GROUPS;
+        if groups.any(|other| collision_groups.is_member_of(other) != (other == group)) {
+            for other in ONE_WAY_BODY_GROUPS {
+                collision_groups.modify_membership(other, other == group);
+            }
+            collider.set_collision_groups(collision_groups);
+        }
+
+        // Small upward velocities are ignored so that a body resting on a
+        // one-way collider doesn't fall through it because of jitter
+        let moving_upward = body.velocity.linear.y < -ONE_WAY_MIN_UPWARD_SPEED;
+        one_way_bodies.push(OneWayBody {
+            group,
+            bounds: collider_bounds(pos, physics_collider),
+            passing_through: moving_upward || dropping_down.contains(entity),
+        });
+    }
+
+    for (&Position(pos), physics_collider) in (positions, physics_colliders).join() {
+        if !physics_collider.one_way {
+            continue;
+        }
+
+        let handle = physics_collider.handle
+            .expect("bug: all colliders should have handles at this point");
+        let collider = colliders.get_mut(handle)
+            .expect("bug: invalid physics collider handle");
+        let mut collision_groups = *collider.collision_groups();
+
+        // Note: the y-axis goes down, so the top of a collider is its minimum y
+        let one_way_bounds = collider_bounds(pos, physics_collider);
+
+        // Groups that at least one body needs to land on, so that a body is
+        // never dropped through a one-way collider because it shares a group
+        // with another body
+        let mut solid_groups = [false; ONE_WAY_BODY_GROUPS.end];
+        let mut passing_groups = [false; ONE_WAY_BODY_GROUPS.end];
+        for &OneWayBody {group, bounds, passing_through} in &one_way_bodies {
+            let was_passing_through = collision_groups.is_group_blacklisted(group);
+
+            // A body resting on a one-way collider may sink slightly into it,
+            // so only bodies that were already passing through need to be
+            // completely above it to land on it. This allows bodies to fall
+            // through a one-way collider that they only partially cleared.
+            let top = if was_passing_through {
+                one_way_bounds.mins().y + ONE_WAY_TOLERANCE
+            } else {
+                one_way_bounds.center().y
+            };
+            let below_top = bounds.maxs().y > top;
+
+            if passing_through || below_top {
+                passing_groups[group] = true;
+            } else {
+                solid_groups[group] = true;
+            }
+        }
+
+        let mut changed = false;
+        for group in ONE_WAY_BODY_GROUPS {
+            let passing_through = passing_groups[group] && !solid_groups[group];
+            if collision_groups.is_group_blacklisted(group) != passing_through {
+                collision_groups.modify_blacklist(group, passing_through);
+                changed = true;
+            }
+        }
+
+        if changed {
+            collider.set_collision_groups(collision_groups);
+        }
+    }
+}
+
+/// Adds the velocity in each `Carried` component (multiplied by `scale`) to the
+/// velocity of the rigid body of the same entity
+fn add_carried_velocities(
+    physics_bodies: &WriteStorage<PhysicsBody>,
+    carried: &ReadStorage<Carried>,
+    bodies: &mut DefaultBodySet<f64>,
+    scale: f64,
+) {
+    for (body, &Carried(velocity)) in (physics_bodies, carried).join() {
+        let handle = body.handle
+            .expect("bug: all bodies should have handles at this point");
+        let rigid_body = bodies.rigid_body_mut(handle)
+            .expect("bug: invalid body handle");
+
+        let mut body_velocity = *rigid_body.velocity();
+        body_velocity.linear += velocity * scale;
+        rigid_body.set_velocity(body_velocity);
+    }
+}
+
+/// Returns the bounding box of the given collider in world coordinates
+fn collider_bounds(pos: Vec2, physics_collider: &PhysicsCollider) -> Aabb {
+    let &PhysicsCollider {ref shape, offset, ..} = physics_collider;
+    shape.bounds().transform_by(&Isometry::new(pos + offset, 0.0))
+}
+
 fn sync_engine_to_physics_bodies(
     positions: &mut WriteStorage<Position>,
     physics_bodies: &mut WriteStorage<PhysicsBody>,
@@ -0,0 +1,69 @@
+use specs::{System, SystemData, World, Read, Entities, ReadStorage, WriteStorage, Join, BitSet, prelude::ResourceId};
+
+use crate::{CollisionsMap, Currency, Wallet, Item, Inventory};
+
+#[derive(SystemData)]
+pub struct Data<'a> {
+    pub collisions: Read<'a, CollisionsMap>,
+    pub entities: Entities<'a>,
+    pub currencies: ReadStorage<'a, Currency>,
+    pub items: ReadStorage<'a, Item>,
+    pub wallets: WriteStorage<'a, Wallet>,
+    pub inventories: WriteStorage<'a, Inventory>,
+}
+
+/// Picks up currency and items when they intersect with an entity that can
+/// collect them
+#[derive(Debug, Default)]
+pub struct PickupSystem;
+
+impl<'a> System<'a> for PickupSystem {
+    type SystemData = Data<'a>;
+
+    fn run(&mut self, data: Self::SystemData) {
+        let Data {
+            collisions,
+            entities,
+            currencies,
+            items,
+            mut wallets,
+            mut inventories,
+        } = data;
+
+        // Keeps track of the entities that were already picked up so that
+        // nothing is collected twice
+        let mut collected = BitSet::new();
+
+        for (entity, mut wallet, mut inventory) in (&entities, (&mut wallets).maybe(), (&mut inventories).maybe()).join() {
+            if wallet.is_none() && inventory.is_none() {
+                continue;
+            }
+
+            let intersecting = &collisions.get(entity).intersecting;
+            for &intersecting_entity in intersecting {
+                if collected.contains(intersecting_entity.id()) {
+                    continue;
+                }
+
+                let mut picked_up = false;
+
+                if let (Some(Wallet(wallet_value)), Some(&Currency(value))) = (&mut wallet, currencies.get(intersecting_entity)) {
+                    *wallet_value += value;
+                    picked_up = true;
+                }
+
+                if let (Some(inventory), Some(Item {name, count})) = (&mut inventory, items.get(intersecting_entity)) {
+                    inventory.add(name, *count);
+                    picked_up = true;
+                }
+
+                if picked_up {
+                    // Remove the collected entity
+                    collected.add(intersecting_entity.id());
+                    entities.delete(intersecting_entity)
+                        .expect("bug: unable to delete collected entity");
+                }
+            }
+        }
+    }
+}
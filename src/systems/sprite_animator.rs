@@ -0,0 +1,32 @@
+use specs::{System, SystemData, World, Read, ReadStorage, WriteStorage, Join, prelude::ResourceId};
+
+use crate::{Clock, AnimatedSprite, Sprite};
+
+#[derive(SystemData)]
+pub struct Data<'a> {
+    pub clock: Read<'a, Clock>,
+    pub animated_sprites: ReadStorage<'a, AnimatedSprite>,
+    pub sprites: WriteStorage<'a, Sprite>,
+}
+
+/// Updates the image of every animated sprite to the current frame of its
+/// animation
+#[derive(Debug, Default)]
+pub struct SpriteAnimator;
+
+impl<'a> System<'a> for SpriteAnimator {
+    type SystemData = Data<'a>;
+
+    fn run(&mut self, data: Self::SystemData) {
+        let Data {
+            clock,
+            animated_sprites,
+            mut sprites,
+        } = data;
+
+        let elapsed = clock.elapsed();
+        for (AnimatedSprite(animation), sprite) in (&animated_sprites, &mut sprites).join() {
+            animation.frame_at(elapsed).update_image(&mut sprite.image);
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use specs::{System, SystemData, World, Read, Write, Entities, ReadStorage, WriteStorage, Join, BitSet, prelude::ResourceId};
+
+use crate::{
+    CollisionsMap,
+    ObjectEntities,
+    SignalEvents,
+    SignalEvent,
+    Interacting,
+    Switch,
+    SignalListener,
+    Sprite,
+};
+
+#[derive(SystemData)]
+pub struct Data<'a> {
+    pub collisions: Read<'a, CollisionsMap>,
+    pub object_entities: Read<'a, ObjectEntities>,
+    pub signal_events: Write<'a, SignalEvents>,
+    pub entities: Entities<'a>,
+    pub interactings: ReadStorage<'a, Interacting>,
+    pub signal_listeners: ReadStorage<'a, SignalListener>,
+    pub switches: WriteStorage<'a, Switch>,
+    pub sprites: WriteStorage<'a, Sprite>,
+}
+
+/// Sends the signal of each switch that an interacting entity overlaps
+#[derive(Debug, Default)]
+pub struct SwitchSystem;
+
+impl<'a> System<'a> for SwitchSystem {
+    type SystemData = Data<'a>;
+
+    fn run(&mut self, data: Self::SystemData) {
+        let Data {
+            collisions,
+            object_entities,
+            mut signal_events,
+            entities,
+            interactings,
+            signal_listeners,
+            mut switches,
+            mut sprites,
+        } = data;
+
+        // A switch is only activated once, even if more than one entity
+        // interacts with it at the same time
+        let mut activated = BitSet::new();
+        for (entity, Interacting) in (&entities, &interactings).join() {
+            for &other in &collisions.get(entity).intersecting {
+                if switches.contains(other) {
+                    activated.add(other.id());
+                }
+            }
+        }
+
+        for (entity, _, switch) in (&entities, &activated, &mut switches).join() {
+            let Switch {signal, target, message, switched_image} = switch;
+            let message = *message;
+
+            if let Some(signal) = signal {
+                let listeners: Vec<_> = (&entities, &signal_listeners).join()
+                    .filter(|(_, listener)| listener.signal == *signal)
+                    .map(|(listener, _)| SignalEvent {target: listener, message})
+                    .collect();
+                signal_events.iter_write(listeners);
+            }
+
+            let target = target.and_then(|target| object_entities.0.get(&target).copied());
+            // The target may have been deleted since the level was loaded
+            if let Some(target) = target.filter(|&target| entities.is_alive(target)) {
+                signal_events.single_write(SignalEvent {target, message});
+            }
+
+            if let (Some(switched_image), Some(sprite)) = (switched_image, sprites.get_mut(entity)) {
+                std::mem::swap(switched_image, &mut sprite.image);
+            }
+        }
+    }
+}
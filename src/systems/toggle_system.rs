@@ -0,0 +1,166 @@
+use specs::{System, SystemData, World, WorldExt, Read, Write, Entities, Entity, WriteStorage, Join, ReaderId, prelude::ResourceId};
+
+use crate::{
+    Clock,
+    CollisionsMap,
+    SignalEvents,
+    SignalEvent,
+    Toggle,
+    ToggledOff,
+    PhysicsCollider,
+    AnimatedSprite,
+    Sprite,
+    Image,
+};
+
+#[derive(SystemData)]
+pub struct Data<'a> {
+    pub clock: Read<'a, Clock>,
+    pub collisions: Write<'a, CollisionsMap>,
+    pub signal_events: Read<'a, SignalEvents>,
+    pub entities: Entities<'a>,
+    pub toggles: WriteStorage<'a, Toggle>,
+    pub toggled_offs: WriteStorage<'a, ToggledOff>,
+    pub physics_colliders: WriteStorage<'a, PhysicsCollider>,
+    pub animated_sprites: WriteStorage<'a, AnimatedSprite>,
+    pub sprites: WriteStorage<'a, Sprite>,
+}
+
+/// Turns entities with a `Toggle` component on and off as time passes or when
+/// they receive a signal
+#[derive(Debug, Default)]
+pub struct ToggleSystem {
+    signal_events_reader_id: Option<ReaderId<SignalEvent>>,
+}
+
+impl<'a> System<'a> for ToggleSystem {
+    type SystemData = Data<'a>;
+
+    fn run(&mut self, data: Self::SystemData) {
+        let Self {signal_events_reader_id} = self;
+
+        let Data {
+            clock,
+            mut collisions,
+            signal_events,
+            entities,
+            mut toggles,
+            mut toggled_offs,
+            mut physics_colliders,
+            mut animated_sprites,
+            mut sprites,
+        } = data;
+
+        let signal_events_reader_id = signal_events_reader_id.as_mut()
+            .expect("reader_id should have been configured during setup");
+
+        for event in signal_events.read(signal_events_reader_id) {
+            let &SignalEvent {target, message} = event;
+            if !entities.is_alive(target) {
+                continue;
+            }
+
+            toggles.entry(target)
+                .expect("bug: signal sent to an entity that is not alive")
+                .or_insert_with(Toggle::default)
+                .receive(message);
+        }
+
+        let now = clock.elapsed();
+
+        let changed: Vec<_> = (&entities, &toggles).join()
+            .filter(|&(entity, toggle)| toggle.is_on_at(now) == toggled_offs.contains(entity))
+            .map(|(entity, toggle)| (entity, toggle.off_image.clone()))
+            .collect();
+
+        for (entity, off_image) in changed {
+            match toggled_offs.remove(entity) {
+                Some(toggled_off) => turn_on(
+                    entity,
+                    toggled_off,
+                    &mut physics_colliders,
+                    &mut animated_sprites,
+                    &mut sprites,
+                ),
+
+                None => {
+                    let toggled_off = turn_off(
+                        entity,
+                        off_image,
+                        &mut collisions,
+                        &mut physics_colliders,
+                        &mut animated_sprites,
+                        &mut sprites,
+                    );
+                    toggled_offs.insert(entity, toggled_off)
+                        .expect("bug: unable to insert toggled off component");
+                },
+            }
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+
+        // register reader id for the signal events
+        let mut signal_events = world.write_resource::<SignalEvents>();
+        self.signal_events_reader_id = Some(signal_events.register_reader());
+    }
+}
+
+/// Removes the collider of the given entity and switches its sprite to the
+/// given image (if any)
+///
+/// Returns everything needed to turn the entity back on.
+fn turn_off(
+    entity: Entity,
+    off_image: Option<Image>,
+    collisions: &mut CollisionsMap,
+    physics_colliders: &mut WriteStorage<PhysicsCollider>,
+    animated_sprites: &mut WriteStorage<AnimatedSprite>,
+    sprites: &mut WriteStorage<Sprite>,
+) -> ToggledOff {
+    let collider = physics_colliders.remove(entity);
+    if collider.is_some() {
+        collisions.remove_collisions_with(entity);
+    }
+
+    let mut on_image = None;
+    let mut animation = None;
+    if let (Some(off_image), Some(sprite)) = (off_image, sprites.get_mut(entity)) {
+        on_image = Some(std::mem::replace(&mut sprite.image, off_image));
+        // The animation would immediately replace the image
+        animation = animated_sprites.remove(entity);
+    }
+
+    ToggledOff {collider, on_image, animation}
+}
+
+/// Restores everything that was removed from the given entity when it was
+/// turned off
+fn turn_on(
+    entity: Entity,
+    toggled_off: ToggledOff,
+    physics_colliders: &mut WriteStorage<PhysicsCollider>,
+    animated_sprites: &mut WriteStorage<AnimatedSprite>,
+    sprites: &mut WriteStorage<Sprite>,
+) {
+    let ToggledOff {collider, on_image, animation} = toggled_off;
+
+    if let Some(collider) = collider {
+        // The collider was removed from the physics engine along with the
+        // component, so it needs to be added again with a new handle
+        let collider = PhysicsCollider {handle: None, ..collider};
+        physics_colliders.insert(entity, collider)
+            .expect("bug: unable to insert physics collider component");
+    }
+
+    if let (Some(on_image), Some(sprite)) = (on_image, sprites.get_mut(entity)) {
+        sprite.image = on_image;
+    }
+
+    if let Some(animation) = animation {
+        animated_sprites.insert(entity, animation)
+            .expect("bug: unable to insert animated sprite component");
+    }
+}
@@ -1,20 +1,184 @@
-use std::path::Path;
+mod xml_tree;
+mod resolve;
+mod groups;
+
+use std::io;
+use std::str::FromStr;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::{Align, Vec2, AssetSource, SharedAssetSource, filesystem_source};
+
+use xml_tree::Element;
+
+pub(crate) use resolve::{
+    FLIPPED_HORIZONTALLY_FLAG,
+    FLIPPED_VERTICALLY_FLAG,
+    FLIPPED_DIAGONALLY_FLAG,
+    ALL_FLIP_FLAGS,
+};
+
+#[derive(Debug, Error)]
+pub enum TileMapError {
+    #[error(transparent)]
+    TiledError(#[from] tiled::TiledError),
+    #[error("Error with path `{0}`: {1}")]
+    IOError(PathBuf, io::Error),
+    #[error("Error while parsing `{0}`: {1}")]
+    XmlError(PathBuf, xml::reader::Error),
+    #[error("Invalid value `{value}` for attribute `{attr}` in `{path}`")]
+    InvalidAttribute {
+        path: PathBuf,
+        attr: &'static str,
+        value: String,
+    },
+    #[error("Invalid value `{value}` for property `{prop}` in `{path}` (expected {expected_type})")]
+    InvalidProperty {
+        path: PathBuf,
+        prop: &'static str,
+        value: String,
+        expected_type: &'static str,
+    },
+    #[error("Missing attribute `{attr}` in `{path}`")]
+    MissingAttribute {
+        path: PathBuf,
+        attr: &'static str,
+    },
+    #[error("Missing `<{element}>` element in `{path}`")]
+    MissingElement {
+        path: PathBuf,
+        element: &'static str,
+    },
+}
+
+/// Data from a `<tileset>` tag that is not provided by the tiled crate
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TilesetExtras {
+    /// The alignment used for tile objects that use tiles from this tileset
+    ///
+    /// If `None`, the alignment was left unspecified in the tileset.
+    pub object_alignment: Option<Align>,
+    /// The offset (in pixels) applied when drawing tiles from this tileset
+    pub tile_offset: Vec2,
+}
 
 #[derive(Debug)]
 pub struct TileMap {
     map: tiled::Map,
+    /// The source that the map and all of the files it references are loaded
+    /// from
+    source: SharedAssetSource,
+    /// The directory containing the map file within its source
+    base_dir: PathBuf,
+    /// Additional data for each tileset, indexed by the first GID of the
+    /// tileset
+    tileset_extras: HashMap<u32, TilesetExtras>,
+    /// The offset of each object group, in the same order as the object groups
+    /// in the map
+    object_group_offsets: Vec<Vec2>,
 }
 
 impl TileMap {
-    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, tiled::TiledError> {
-        let map = tiled::parse_file(path.as_ref())?;
-        Ok(Self {map})
+    /// Opens the map file at the given path on the filesystem
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, TileMapError> {
+        Self::load(filesystem_source(), path)
+    }
+
+    /// Loads the map file at the given path within the given asset source
+    pub fn load<P: AsRef<Path>>(source: SharedAssetSource, path: P) -> Result<Self, TileMapError> {
+        let path = path.as_ref();
+        let data = source.read(path)
+            .map_err(|err| TileMapError::IOError(path.to_path_buf(), err))?;
+        Self::from_reader(source, path, &data[..])
+    }
+
+    /// Loads a map from the given reader
+    ///
+    /// The path is the location of the map within the asset source. Any files
+    /// referenced by the map (tilesets, templates, images, etc.) are found
+    /// relative to that path. The path is also used in error messages.
+    pub fn from_reader<P: AsRef<Path>, R: io::Read>(
+        source: SharedAssetSource,
+        path: P,
+        reader: R,
+    ) -> Result<Self, TileMapError> {
+        let path = path.as_ref();
+        let base_dir = path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+
+        let root = Element::parse(reader)
+            .map_err(|err| TileMapError::XmlError(path.to_path_buf(), err))?;
+        Self::from_root(source, path, base_dir, root)
+    }
+
+    fn from_root(
+        source: SharedAssetSource,
+        path: &Path,
+        base_dir: PathBuf,
+        mut root: Element,
+    ) -> Result<Self, TileMapError> {
+        // External tilesets and object templates are resolved before the map
+        // is passed to the tiled crate
+        let mut tileset_origins = resolve::inline_tilesets(&*source, &base_dir, &mut root)?;
+        resolve::resolve_templates(&*source, path, &base_dir, &mut root, &mut tileset_origins)?;
+        groups::flatten_groups(path, &mut root)?;
+        resolve::normalize_object_properties(&mut root);
+
+        // No external files remain, so the tiled crate doesn't need a path
+        let map = tiled::parse(root.to_string().as_bytes())?;
+
+        let mut tileset_extras = HashMap::new();
+        let tilesets = root.child_elements().filter(|child| child.name == "tileset");
+        for (tileset, origin) in tilesets.zip(&tileset_origins) {
+            // Errors are reported with the path of the file that the tileset
+            // was originally defined in
+            let tileset_path = origin.source.as_deref().unwrap_or(path);
+            let extras = tileset_extras_from(tileset_path, tileset)?;
+            tileset_extras.insert(origin.first_gid, extras);
+        }
+
+        // The tiled crate provides object groups in the order they appear in
+        // the file. Group layers have already been flattened, so their offsets
+        // are included in the offsets of these object groups.
+        let object_group_offsets = root.child_elements()
+            .filter(|child| child.name == "objectgroup")
+            .map(|group| Ok(Vec2::new(
+                parse_attr(path, group, "offsetx")?.unwrap_or(0.0),
+                parse_attr(path, group, "offsety")?.unwrap_or(0.0),
+            )))
+            .collect::<Result<_, TileMapError>>()?;
+
+        Ok(Self {map, source, base_dir, tileset_extras, object_group_offsets})
     }
 
     pub fn as_map(&self) -> &tiled::Map {
         &self.map
     }
 
+    /// The source that any files referenced by the map should be loaded from
+    pub fn source(&self) -> &SharedAssetSource {
+        &self.source
+    }
+
+    /// The directory containing the map file within its source
+    ///
+    /// Relative paths in the map are relative to this directory.
+    pub fn base_dir(&self) -> &Path {
+        &self.base_dir
+    }
+
+    /// Returns the additional data for the tileset with the given first GID
+    pub fn tileset_extras(&self, first_gid: u32) -> Option<&TilesetExtras> {
+        self.tileset_extras.get(&first_gid)
+    }
+
+    /// Returns the offset of the object group at the given index in the map's
+    /// list of object groups
+    pub fn object_group_offset(&self, index: usize) -> Vec2 {
+        self.object_group_offsets.get(index).copied().unwrap_or_default()
+    }
+
     pub fn tile_width(&self) -> u32 {
         self.map.tile_width
     }
@@ -23,3 +187,59 @@ impl TileMap {
         self.map.tile_height
     }
 }
+
+fn parse_xml_file(source: &dyn AssetSource, path: &Path) -> Result<Element, TileMapError> {
+    let data = source.read(path)
+        .map_err(|err| TileMapError::IOError(path.to_path_buf(), err))?;
+    Element::parse(&data[..])
+        .map_err(|err| TileMapError::XmlError(path.to_path_buf(), err))
+}
+
+/// Parses the value of the given attribute (if present)
+///
+/// The path is only used for error messages.
+fn parse_attr<T: FromStr>(
+    path: &Path,
+    element: &Element,
+    attr: &'static str,
+) -> Result<Option<T>, TileMapError> {
+    element.attr(attr).map(|value| value.parse().map_err(|_| TileMapError::InvalidAttribute {
+        path: path.to_path_buf(),
+        attr,
+        value: value.to_string(),
+    })).transpose()
+}
+
+/// Reads the additional tileset data from a `<tileset>` element
+///
+/// The path is only used for error messages.
+fn tileset_extras_from(path: &Path, tileset: &Element) -> Result<TilesetExtras, TileMapError> {
+    // See: https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#tileset
+    let object_alignment = match tileset.attr("objectalignment") {
+        None | Some("unspecified") => None,
+        Some("topleft") => Some(Align::TopLeft),
+        Some("top") => Some(Align::Top),
+        Some("topright") => Some(Align::TopRight),
+        Some("left") => Some(Align::Left),
+        Some("center") => Some(Align::Center),
+        Some("right") => Some(Align::Right),
+        Some("bottomleft") => Some(Align::BottomLeft),
+        Some("bottom") => Some(Align::Bottom),
+        Some("bottomright") => Some(Align::BottomRight),
+        Some(value) => return Err(TileMapError::InvalidAttribute {
+            path: path.to_path_buf(),
+            attr: "objectalignment",
+            value: value.to_string(),
+        }),
+    };
+
+    let tile_offset = match tileset.find_child("tileoffset") {
+        Some(offset) => Vec2::new(
+            parse_attr(path, offset, "x")?.unwrap_or(0.0),
+            parse_attr(path, offset, "y")?.unwrap_or(0.0),
+        ),
+        None => Vec2::default(),
+    };
+
+    Ok(TilesetExtras {object_alignment, tile_offset})
+}
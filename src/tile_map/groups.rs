@@ -0,0 +1,139 @@
+//! Flattens group layers so that every layer in the map is at the top level
+//!
+//! The tiled crate does not know about group layers. It finds the layers
+//! nested within them, but ignores the offset, opacity, and visibility of each
+//! group. Flattening the groups beforehand lets that information be composed
+//! into each layer.
+
+use std::path::Path;
+
+use super::{TileMapError, parse_attr};
+use super::xml_tree::{Element, Node};
+
+/// The custom property that determines whether the layers in a hidden group
+/// are hidden too
+///
+/// Layers are not hidden by default since a layer's visibility in Tiled does
+/// not affect its visibility in the game.
+const HIDE_CHILDREN_PROP: &str = "hide_children";
+
+/// The offset and opacity accumulated from all of the groups that contain a
+/// layer
+#[derive(Debug, Clone, Copy)]
+struct GroupParams {
+    offset_x: f64,
+    offset_y: f64,
+    opacity: f64,
+}
+
+/// Replaces every group layer in the map with the layers within it
+///
+/// The offset and opacity of each group are composed with the offset and
+/// opacity of the layers inside it. The layers in a hidden group are removed if
+/// the group sets the `hide_children` property.
+///
+/// The path is only used for error messages.
+pub(super) fn flatten_groups(path: &Path, root: &mut Element) -> Result<(), TileMapError> {
+    let params = GroupParams {offset_x: 0.0, offset_y: 0.0, opacity: 1.0};
+    let children = std::mem::take(&mut root.children);
+    root.children = flatten_children(path, children, params)?;
+    Ok(())
+}
+
+fn flatten_children(
+    path: &Path,
+    children: Vec<Node>,
+    params: GroupParams,
+) -> Result<Vec<Node>, TileMapError> {
+    let mut flattened = Vec::with_capacity(children.len());
+    for child in children {
+        let mut element = match child {
+            Node::Element(element) => element,
+            Node::Text(_) => {
+                flattened.push(child);
+                continue;
+            },
+        };
+
+        match element.name.as_str() {
+            "group" => {
+                let visible = parse_attr::<u8>(path, &element, "visible")?.unwrap_or(1) != 0;
+                if !visible && hide_children(path, &element)? {
+                    continue;
+                }
+
+                let group_params = GroupParams {
+                    offset_x: params.offset_x + parse_attr(path, &element, "offsetx")?.unwrap_or(0.0),
+                    offset_y: params.offset_y + parse_attr(path, &element, "offsety")?.unwrap_or(0.0),
+                    opacity: params.opacity * parse_attr(path, &element, "opacity")?.unwrap_or(1.0),
+                };
+
+                // The properties of the group itself are discarded since they
+                // would otherwise be mistaken for the properties of the map
+                let children = element.children.into_iter()
+                    .filter(|child| !matches!(child, Node::Element(elem) if elem.name == "properties"))
+                    .collect();
+                flattened.extend(flatten_children(path, children, group_params)?);
+            },
+
+            "layer" | "imagelayer" | "objectgroup" => {
+                apply_group_params(path, &mut element, params)?;
+                flattened.push(Node::Element(element));
+            },
+
+            _ => flattened.push(Node::Element(element)),
+        }
+    }
+
+    Ok(flattened)
+}
+
+/// Composes the given parameters with the offset and opacity of the layer
+fn apply_group_params(
+    path: &Path,
+    layer: &mut Element,
+    params: GroupParams,
+) -> Result<(), TileMapError> {
+    let GroupParams {offset_x, offset_y, opacity} = params;
+
+    // Only modify attributes that would actually change to avoid adding them
+    // unnecessarily
+    if offset_x != 0.0 {
+        let layer_offset_x: f64 = parse_attr(path, layer, "offsetx")?.unwrap_or(0.0);
+        layer.set_attr("offsetx", (layer_offset_x + offset_x).to_string());
+    }
+    if offset_y != 0.0 {
+        let layer_offset_y: f64 = parse_attr(path, layer, "offsety")?.unwrap_or(0.0);
+        layer.set_attr("offsety", (layer_offset_y + offset_y).to_string());
+    }
+    if opacity != 1.0 {
+        let layer_opacity: f64 = parse_attr(path, layer, "opacity")?.unwrap_or(1.0);
+        layer.set_attr("opacity", (layer_opacity * opacity).to_string());
+    }
+
+    Ok(())
+}
+
+/// Returns the value of the `hide_children` property of the given group
+fn hide_children(path: &Path, group: &Element) -> Result<bool, TileMapError> {
+    let prop = group.find_child("properties")
+        .into_iter()
+        .flat_map(|props| props.child_elements())
+        .find(|prop| prop.attr("name") == Some(HIDE_CHILDREN_PROP));
+
+    let prop = match prop {
+        Some(prop) => prop,
+        None => return Ok(false),
+    };
+
+    match (prop.attr("type"), prop.attr("value")) {
+        (Some("bool"), Some("true")) => Ok(true),
+        (Some("bool"), Some("false")) => Ok(false),
+        (_, value) => Err(TileMapError::InvalidProperty {
+            path: path.to_path_buf(),
+            prop: HIDE_CHILDREN_PROP,
+            value: value.unwrap_or("").to_string(),
+            expected_type: "bool",
+        }),
+    }
+}
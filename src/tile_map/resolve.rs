@@ -0,0 +1,388 @@
+//! Resolves the external files referenced by a map so that everything the
+//! tiled crate needs is available in a single document
+//!
+//! The tiled crate loads external tilesets, but it leaves the image paths
+//! within them relative to the tileset file. It also doesn't support object
+//! templates at all. Both are handled here by inlining the external files into
+//! the map before it is given to the tiled crate. Properties that refer to
+//! other objects are also rewritten here, since the tiled crate fails to parse
+//! them.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::AssetSource;
+
+use super::{TileMapError, parse_xml_file, parse_attr};
+use super::xml_tree::{Element, Node};
+
+// The bits of a GID that store how the tile is flipped
+// See: https://doc.mapeditor.org/en/stable/reference/global-tile-ids/
+pub(crate) const FLIPPED_HORIZONTALLY_FLAG: u32 = 0x80000000;
+pub(crate) const FLIPPED_VERTICALLY_FLAG: u32 = 0x40000000;
+pub(crate) const FLIPPED_DIAGONALLY_FLAG: u32 = 0x20000000;
+pub(crate) const ALL_FLIP_FLAGS: u32 = FLIPPED_HORIZONTALLY_FLAG
+    | FLIPPED_VERTICALLY_FLAG
+    | FLIPPED_DIAGONALLY_FLAG;
+
+/// A tileset in the map and the file it was defined in
+#[derive(Debug, Clone)]
+pub(super) struct TilesetOrigin {
+    pub first_gid: u32,
+    /// The canonical path of the external tileset file, or `None` if the
+    /// tileset was defined in the map file itself
+    pub source: Option<PathBuf>,
+}
+
+/// The tilesets of the map while templates are being resolved
+#[derive(Debug)]
+struct Tilesets<'a> {
+    source: &'a dyn AssetSource,
+    origins: &'a mut Vec<TilesetOrigin>,
+    /// Tilesets used by templates that were not already in the map
+    added: Vec<Element>,
+    /// The first GID available for the next tileset added to the map
+    next_gid: u32,
+}
+
+/// An object template loaded from a `.tx` file
+#[derive(Debug)]
+struct Template {
+    object: Element,
+    /// The tileset used by the template object (if any)
+    tileset: Option<TemplateTileset>,
+}
+
+/// A reference to a tileset from a template file
+#[derive(Debug)]
+struct TemplateTileset {
+    /// The first GID of the tileset within the template file
+    first_gid: u32,
+    /// The canonical path of the tileset file
+    path: PathBuf,
+    /// The path of the tileset file relative to the template file
+    source: PathBuf,
+}
+
+/// Replaces every external tileset in the map with the contents of its file
+///
+/// Image paths within the tileset are rewritten to be relative to the map.
+pub(super) fn inline_tilesets(
+    source: &dyn AssetSource,
+    base_dir: &Path,
+    root: &mut Element,
+) -> Result<Vec<TilesetOrigin>, TileMapError> {
+    let mut origins = Vec::new();
+    for tileset in root.child_elements_mut().filter(|child| child.name == "tileset") {
+        let first_gid = match tileset.attr("firstgid").map(|gid| gid.parse::<u32>()) {
+            Some(Ok(first_gid)) => first_gid,
+            // The tiled crate will produce an error for this
+            _ => continue,
+        };
+
+        let tileset_source = match tileset.remove_attr("source") {
+            Some(tileset_source) => {
+                let tileset_path = canonicalize(source, &base_dir.join(&tileset_source))?;
+                // Keep the image paths relative to the map
+                let tileset_dir = Path::new(&tileset_source).parent().unwrap_or_else(|| Path::new(""));
+                *tileset = load_tileset(source, &tileset_path, tileset_dir)?;
+                tileset.set_attr("firstgid", first_gid.to_string());
+                Some(tileset_path)
+            },
+            None => None,
+        };
+
+        origins.push(TilesetOrigin {first_gid, source: tileset_source});
+    }
+
+    Ok(origins)
+}
+
+/// Loads the tileset file at the given path, prefixing every image path and
+/// file property in the tileset with the given directory
+fn load_tileset(
+    source: &dyn AssetSource,
+    tileset_path: &Path,
+    tileset_dir: &Path,
+) -> Result<Element, TileMapError> {
+    let mut tileset = parse_xml_file(source, tileset_path)?;
+    rewrite_asset_paths(tileset_dir, &mut tileset);
+    Ok(tileset)
+}
+
+/// Prefixes the source path of every `<image>` element and the value of every
+/// file property with the given directory
+///
+/// Tiled stores these paths relative to the file they are in, so this keeps
+/// them relative to the map when an external file is inlined into it.
+fn rewrite_asset_paths(dir: &Path, element: &mut Element) {
+    for child in element.child_elements_mut() {
+        let path_attr = match child.name.as_str() {
+            "image" => Some("source"),
+            "property" if child.attr("type") == Some("file") => Some("value"),
+            _ => None,
+        };
+
+        // An empty file property means that no file was selected
+        let path = path_attr.and_then(|attr| child.attr(attr)).filter(|path| !path.is_empty());
+        if let (Some(attr), Some(path)) = (path_attr, path) {
+            let path = dir.join(path).to_string_lossy().into_owned();
+            child.set_attr(attr, path);
+        }
+
+        rewrite_asset_paths(dir, child);
+    }
+}
+
+/// Replaces every template instance in the map with an object that has all of
+/// the data from its template, keeping any data overridden by the instance
+///
+/// Tilesets used by templates are added to the map if the map does not already
+/// contain them.
+pub(super) fn resolve_templates(
+    source: &dyn AssetSource,
+    map_path: &Path,
+    base_dir: &Path,
+    root: &mut Element,
+    origins: &mut Vec<TilesetOrigin>,
+) -> Result<(), TileMapError> {
+    // Every tileset is inline at this point, so the tile count is available
+    let mut next_gid = 1;
+    for tileset in root.child_elements().filter(|child| child.name == "tileset") {
+        let first_gid: u32 = parse_attr(map_path, tileset, "firstgid")?.unwrap_or(1);
+        let tile_count: u32 = parse_attr(map_path, tileset, "tilecount")?.unwrap_or(0);
+        next_gid = next_gid.max(first_gid + tile_count);
+    }
+
+    let mut tilesets = Tilesets {source, origins, added: Vec::new(), next_gid};
+    let mut templates = HashMap::new();
+    resolve_templates_in(base_dir, root, &mut templates, &mut tilesets)?;
+    let new_tilesets = tilesets.added;
+
+    // Keep the tilesets in order of their first GIDs by inserting the new
+    // tilesets after the last tileset in the map
+    let insert_index = root.children.iter()
+        .rposition(|child| matches!(child, Node::Element(elem) if elem.name == "tileset"))
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    root.children.splice(insert_index..insert_index, new_tilesets.into_iter().map(Node::Element));
+
+    Ok(())
+}
+
+/// Resolves the templates of the objects in every object group within the given
+/// element and any group layers within it
+fn resolve_templates_in(
+    base_dir: &Path,
+    element: &mut Element,
+    templates: &mut HashMap<PathBuf, Template>,
+    tilesets: &mut Tilesets,
+) -> Result<(), TileMapError> {
+    for child in element.child_elements_mut() {
+        match child.name.as_str() {
+            "objectgroup" => {
+                let objects = child.child_elements_mut()
+                    .filter(|object| object.name == "object");
+                for object in objects {
+                    let template = match object.remove_attr("template") {
+                        Some(template) => template,
+                        None => continue,
+                    };
+
+                    // Template paths are relative to the map file
+                    let template_path = canonicalize(tilesets.source, &base_dir.join(&template))?;
+                    if !templates.contains_key(&template_path) {
+                        let template = load_template(tilesets.source, &template_path)?;
+                        templates.insert(template_path.clone(), template);
+                    }
+                    let template_data = &templates[&template_path];
+
+                    let template_dir = Path::new(&template).parent().unwrap_or_else(|| Path::new(""));
+                    apply_template(&template_path, template_dir, template_data, object, tilesets)?;
+                }
+            },
+
+            "group" => resolve_templates_in(base_dir, child, templates, tilesets)?,
+
+            _ => {},
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads the template file at the given canonical path
+fn load_template(source: &dyn AssetSource, template_path: &Path) -> Result<Template, TileMapError> {
+    let root = parse_xml_file(source, template_path)?;
+    let object = root.find_child("object").cloned()
+        .ok_or_else(|| TileMapError::MissingElement {
+            path: template_path.to_path_buf(),
+            element: "object",
+        })?;
+
+    let tileset = match root.find_child("tileset") {
+        Some(tileset) => {
+            let first_gid = parse_attr(template_path, tileset, "firstgid")?.unwrap_or(1);
+            let tileset_source = tileset.attr("source").ok_or_else(|| TileMapError::MissingAttribute {
+                path: template_path.to_path_buf(),
+                attr: "source",
+            })?;
+
+            // Tileset paths are relative to the template file
+            let template_dir = template_path.parent().unwrap_or_else(|| Path::new(""));
+            let tileset_path = canonicalize(source, &template_dir.join(tileset_source))?;
+
+            Some(TemplateTileset {
+                first_gid,
+                path: tileset_path,
+                source: PathBuf::from(tileset_source),
+            })
+        },
+        None => None,
+    };
+
+    Ok(Template {object, tileset})
+}
+
+/// Merges the template into the given object
+///
+/// Attributes and properties set on the object override the ones from the
+/// template. The template directory is the directory containing the template
+/// relative to the map.
+fn apply_template(
+    template_path: &Path,
+    template_dir: &Path,
+    template: &Template,
+    object: &mut Element,
+    tilesets: &mut Tilesets,
+) -> Result<(), TileMapError> {
+    let Template {object: template_object, tileset: template_tileset} = template;
+
+    for (name, value) in &template_object.attributes {
+        if object.attr(name).is_some() {
+            continue;
+        }
+
+        // The GID in the template refers to the tileset in the template file,
+        // so it needs to be mapped to the GID of that tileset in the map
+        let value = if name == "gid" {
+            let gid: u32 = parse_attr(template_path, template_object, "gid")?.unwrap_or(0);
+            let template_tileset = template_tileset.as_ref()
+                .ok_or_else(|| TileMapError::MissingElement {
+                    path: template_path.to_path_buf(),
+                    element: "tileset",
+                })?;
+            let first_gid = map_first_gid(template_dir, template_tileset, tilesets)?;
+
+            let flags = gid & ALL_FLIP_FLAGS;
+            let local_id = (gid & !ALL_FLIP_FLAGS).saturating_sub(template_tileset.first_gid);
+            ((first_gid + local_id) | flags).to_string()
+
+        } else {
+            value.clone()
+        };
+
+        object.attributes.push((name.clone(), value));
+    }
+
+    // Properties are merged individually, but the shape of the object can only
+    // be overridden as a whole
+    let has_shape = object.child_elements().any(|child| child.name != "properties");
+    for child in template_object.child_elements() {
+        // File properties in the template are relative to the template file
+        let mut child = child.clone();
+        rewrite_asset_paths(template_dir, &mut child);
+
+        if child.name == "properties" {
+            merge_properties(&child, object);
+
+        } else if !has_shape {
+            object.children.push(Node::Element(child));
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds any properties from the template that are not set on the object
+fn merge_properties(template_props: &Element, object: &mut Element) {
+    let index = object.children.iter()
+        .position(|child| matches!(child, Node::Element(elem) if elem.name == "properties"));
+    let props = match index {
+        Some(index) => match &mut object.children[index] {
+            Node::Element(props) => props,
+            Node::Text(_) => unreachable!(),
+        },
+        None => {
+            object.children.push(Node::Element(Element {
+                name: "properties".to_string(),
+                attributes: Vec::new(),
+                children: Vec::new(),
+            }));
+            match object.children.last_mut() {
+                Some(Node::Element(props)) => props,
+                _ => unreachable!(),
+            }
+        },
+    };
+
+    for prop in template_props.child_elements() {
+        let name = prop.attr("name");
+        let is_overridden = props.child_elements()
+            .any(|obj_prop| obj_prop.attr("name") == name);
+        if !is_overridden {
+            props.children.push(Node::Element(prop.clone()));
+        }
+    }
+}
+
+/// Returns the first GID of the given template tileset in the map, adding the
+/// tileset to the map if it isn't there already
+fn map_first_gid(
+    template_dir: &Path,
+    template_tileset: &TemplateTileset,
+    tilesets: &mut Tilesets,
+) -> Result<u32, TileMapError> {
+    let TemplateTileset {first_gid: _, path: tileset_path, source: tileset_source} = template_tileset;
+
+    let existing = tilesets.origins.iter()
+        .find(|origin| origin.source.as_ref() == Some(tileset_path));
+    if let Some(origin) = existing {
+        return Ok(origin.first_gid);
+    }
+
+    // Keep the image paths relative to the map
+    let tileset_source = template_dir.join(tileset_source);
+    let tileset_dir = tileset_source.parent().unwrap_or_else(|| Path::new(""));
+    let mut tileset = load_tileset(tilesets.source, tileset_path, tileset_dir)?;
+    let tile_count: u32 = parse_attr(tileset_path, &tileset, "tilecount")?.unwrap_or(0);
+
+    let first_gid = tilesets.next_gid;
+    tilesets.next_gid += tile_count;
+
+    tileset.set_attr("firstgid", first_gid.to_string());
+    tilesets.added.push(tileset);
+    tilesets.origins.push(TilesetOrigin {first_gid, source: Some(tileset_path.clone())});
+
+    Ok(first_gid)
+}
+
+/// Changes the type of every property that refers to an object into an int
+/// property containing the ID of that object
+///
+/// Tiled stores object references as the ID of the object, with 0 meaning that
+/// no object was selected.
+pub(super) fn normalize_object_properties(element: &mut Element) {
+    for child in element.child_elements_mut() {
+        if child.name == "property" && child.attr("type") == Some("object") {
+            child.set_attr("type", "int");
+        }
+
+        normalize_object_properties(child);
+    }
+}
+
+fn canonicalize(source: &dyn AssetSource, path: &Path) -> Result<PathBuf, TileMapError> {
+    source.canonicalize(path)
+        .map_err(|err| TileMapError::IOError(path.to_path_buf(), err))
+}
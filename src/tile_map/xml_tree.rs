@@ -0,0 +1,160 @@
+use std::fmt;
+use std::io::Read;
+
+use xml::reader::{EventReader, XmlEvent};
+
+/// A node in an XML document
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Node {
+    Element(Element),
+    Text(String),
+}
+
+/// A minimal in-memory representation of an XML element
+///
+/// Namespaces, comments, and processing instructions are discarded since they
+/// are not used in Tiled files.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Element {
+    pub name: String,
+    /// The attributes of the element, in the order they appear in the document
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+impl Element {
+    /// Parses an XML document and returns its root element
+    pub fn parse<R: Read>(reader: R) -> Result<Self, xml::reader::Error> {
+        // Elements that have been started but not yet ended
+        let mut stack: Vec<Element> = Vec::new();
+
+        for event in EventReader::new(reader) {
+            match event? {
+                XmlEvent::StartElement {name, attributes, namespace: _} => {
+                    stack.push(Element {
+                        name: name.local_name,
+                        attributes: attributes.into_iter()
+                            .map(|attr| (attr.name.local_name, attr.value))
+                            .collect(),
+                        children: Vec::new(),
+                    });
+                },
+
+                XmlEvent::EndElement {name: _} => {
+                    let element = stack.pop()
+                        .expect("bug: the XML reader should ensure that every end tag has a start tag");
+
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(Node::Element(element)),
+                        None => return Ok(element),
+                    }
+                },
+
+                XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                    if let Some(parent) = stack.last_mut() {
+                        parent.children.push(Node::Text(text));
+                    }
+                },
+
+                XmlEvent::StartDocument {..} |
+                XmlEvent::EndDocument |
+                XmlEvent::ProcessingInstruction {..} |
+                XmlEvent::Comment(_) |
+                XmlEvent::Whitespace(_) => {},
+            }
+        }
+
+        unreachable!("bug: the XML reader should produce an error if the document has no root element")
+    }
+
+    /// Returns the value of the attribute with the given name
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.iter()
+            .find(|(attr_name, _)| attr_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the value of the given attribute, adding it if it is not present
+    pub fn set_attr(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.attributes.iter_mut().find(|(attr_name, _)| attr_name == name) {
+            Some((_, old_value)) => *old_value = value,
+            None => self.attributes.push((name.to_string(), value)),
+        }
+    }
+
+    /// Removes the attribute with the given name, returning its value
+    pub fn remove_attr(&mut self, name: &str) -> Option<String> {
+        let index = self.attributes.iter().position(|(attr_name, _)| attr_name == name)?;
+        Some(self.attributes.remove(index).1)
+    }
+
+    /// Iterates over the child elements of this element (ignoring text)
+    pub fn child_elements(&self) -> impl Iterator<Item=&Element> {
+        self.children.iter().filter_map(|child| match child {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    /// Iterates mutably over the child elements of this element (ignoring text)
+    pub fn child_elements_mut(&mut self) -> impl Iterator<Item=&mut Element> {
+        self.children.iter_mut().filter_map(|child| match child {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    /// Returns the first child element with the given name
+    pub fn find_child(&self, name: &str) -> Option<&Element> {
+        self.child_elements().find(|child| child.name == name)
+    }
+}
+
+/// Writes the element as XML, including all of its children
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}", self.name)?;
+        for (name, value) in &self.attributes {
+            write!(f, " {}=\"{}\"", name, Escaped(value))?;
+        }
+
+        if self.children.is_empty() {
+            return write!(f, "/>");
+        }
+
+        write!(f, ">")?;
+        for child in &self.children {
+            match child {
+                Node::Element(element) => write!(f, "{}", element)?,
+                Node::Text(text) => write!(f, "{}", Escaped(text))?,
+            }
+        }
+        write!(f, "</{}>", self.name)
+    }
+}
+
+/// Escapes the characters that are not allowed to appear as-is in XML text or
+/// attribute values
+struct Escaped<'a>(&'a str);
+
+impl<'a> fmt::Display for Escaped<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for ch in self.0.chars() {
+            match ch {
+                '&' => write!(f, "&amp;")?,
+                '<' => write!(f, "&lt;")?,
+                '>' => write!(f, "&gt;")?,
+                '"' => write!(f, "&quot;")?,
+                '\'' => write!(f, "&apos;")?,
+                // Preserve newlines and tabs in attribute values
+                '\n' => write!(f, "&#10;")?,
+                '\t' => write!(f, "&#9;")?,
+                '\r' => write!(f, "&#13;")?,
+                _ => write!(f, "{}", ch)?,
+            }
+        }
+
+        Ok(())
+    }
+}